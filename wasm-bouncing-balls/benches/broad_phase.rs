@@ -0,0 +1,68 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use wasm_bouncing_balls::bench::{BallSpec, Scene, NAMES};
+
+//画面で選べる広域当たり判定の方式(動的ツリー、BVHの中点分割とSAH、一様グリッド、Sort and Sweep)を比べる
+//どれも1回ごとに画面と同じWorld::stepで1ステップ進める 広域当たり判定以外(CCDや接触の解決)の時間はどれにも同じだけ入る
+//絞り込み具合を比べられるように、ボール1個あたりに調べたAABBの数も表示する(数えている方式だけ)
+
+const SEED: u64 = 0x5eed;
+
+//xorshift 毎回同じ配置になるように自前で持つ
+struct Rng(u64);
+
+impl Rng {
+    fn next_f64(&mut self, min: f64, max: f64) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        min + (self.0 >> 11) as f64 / (1u64 << 53) as f64 * (max - min)
+    }
+}
+
+//画面(1280x720に1000個)と同じ混み具合になるように、ボールの数に合わせて広さを変える
+fn scene(count: usize, broad_phase: &str) -> Scene {
+    let scale = (count as f64 / 1000.0).sqrt();
+    let (width, height) = (1280.0 * scale, 720.0 * scale);
+
+    let mut rng = Rng(SEED);
+    let balls: Vec<BallSpec> = (0..count)
+        .map(|_| {
+            let size = rng.next_f64(5.0, 10.0);
+            BallSpec {
+                x: rng.next_f64(size, width - size),
+                y: rng.next_f64(size, height - size),
+                vel_x: rng.next_f64(-120.0, 120.0),
+                vel_y: rng.next_f64(-120.0, 120.0),
+                size,
+            }
+        })
+        .collect();
+    Scene::new(width, height, &balls, broad_phase)
+}
+
+fn broad_phase(c: &mut Criterion) {
+    let mut group = c.benchmark_group("broad_phase");
+    for &count in &[1000, 10000] {
+        for name in NAMES.iter() {
+            let mut scene = scene(count, name);
+            scene.step();
+            if let Some(overlap_tests) = scene.average_overlap_tests() {
+                println!(
+                    "broad_phase/{}/{}: {:.1} overlap tests per query",
+                    name, count, overlap_tests
+                );
+            }
+            group.bench_with_input(BenchmarkId::new(*name, count), &count, |b, _| {
+                b.iter(|| {
+                    scene.step();
+                    black_box(scene.pairs())
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, broad_phase);
+criterion_main!(benches);
@@ -1,17 +1,23 @@
 mod html_cast;
+mod physics;
+mod render;
 mod utils;
 use std::{
-    cell::{Ref, RefCell, RefMut},
-    clone,
-    rc::{self, Rc},
+    cell::{RefCell, RefMut},
+    rc::Rc,
 };
 
 use html_cast::*;
 use js_sys::Math;
+use physics::broad_phase;
+use physics::response::CollisionSettings;
+use physics::world::{World, TIMESTEP};
+use physics::Ball;
+use render::Renderer;
 use wasm_bindgen::prelude::*;
 use web_sys::{
-    console, CanvasRenderingContext2d, Event, HtmlButtonElement, HtmlCanvasElement,
-    HtmlInputElement, HtmlParagraphElement, Performance,
+    console, HtmlButtonElement, HtmlCanvasElement, HtmlInputElement, HtmlParagraphElement,
+    HtmlSelectElement,
 };
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -42,8 +48,9 @@ pub fn start() -> Result<(), JsValue> {
     let height = 720.0;
     canvas.set_height(height as u32);
 
-    let balls: Vec<Ball> = Vec::new();
-    let balls_rc = Rc::new(RefCell::new(balls));
+    let renderer = Renderer::new(canvas);
+    let world = World::new(width, height);
+    let world_rc = Rc::new(RefCell::new(world));
 
     let balls_size = Number(
         &query_selector_to::<HtmlInputElement>(".ball-field")
@@ -51,7 +58,7 @@ pub fn start() -> Result<(), JsValue> {
             .value(),
     );
 
-    balls_init(&balls_rc, balls_size);
+    balls_init(&world_rc, balls_size);
 
     let is_playing_rc = Rc::new(RefCell::new(true));
 
@@ -71,14 +78,14 @@ pub fn start() -> Result<(), JsValue> {
     {
         let balls_size_submit = query_selector_to::<HtmlInputElement>(".ball-submit").unwrap();
 
-        let balls_rc_clone = balls_rc.clone();
+        let world_rc_clone = world_rc.clone();
         let closure: Closure<dyn FnMut()> = Closure::new(move || {
             let balls_size = Number(
                 &query_selector_to::<HtmlInputElement>(".ball-field")
                     .unwrap()
                     .value(),
             );
-            balls_init(&balls_rc_clone, balls_size);
+            balls_init(&world_rc_clone, balls_size);
         });
         balls_size_submit
             .add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())
@@ -86,23 +93,30 @@ pub fn start() -> Result<(), JsValue> {
         closure.forget();
     }
 
-    main_loop(balls_rc.clone(), &is_playing_rc);
+    main_loop(world_rc.clone(), renderer, &is_playing_rc);
 
     Ok(())
 }
 
-fn main_loop(balls_rc: Rc<RefCell<Vec<Ball>>>, is_playing_rc: &Rc<RefCell<bool>>) {
-    let closure: Rc<RefCell<Option<Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
+//requestAnimationFrameに渡すクロージャ 中から自分自身を次のフレームに登録し直すのでRcで共有する
+type FrameClosure = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+fn main_loop(world_rc: Rc<RefCell<World>>, renderer: Renderer, is_playing_rc: &Rc<RefCell<bool>>) {
+    let closure: FrameClosure = Rc::new(RefCell::new(None));
     let closure_clone = closure.clone();
 
     let mut fps = Fps::new();
+    //物理の経過時間もFpsと同じPerformanceのタイムスタンプで測る
+    let performance = web_sys::window().unwrap().performance().unwrap();
 
     let is_playing_rc_clone = is_playing_rc.clone();
     *closure_clone.borrow_mut() = Some(Closure::new(move || {
         if *is_playing_rc_clone.borrow() {
-            update(&mut balls_rc.borrow_mut());
+            update(&mut world_rc.borrow_mut(), &renderer, performance.now());
 
             fps.render();
+        } else {
+            world_rc.borrow_mut().stop_clock();
         }
         request_animation_frame(&closure);
     }));
@@ -111,524 +125,152 @@ fn main_loop(balls_rc: Rc<RefCell<Vec<Ball>>>, is_playing_rc: &Rc<RefCell<bool>>
 }
 
 fn play_pause(is_playing_rc: &Rc<RefCell<bool>>) {
-    let state = is_playing_rc.borrow().clone();
-
-    if state {
-        *is_playing_rc.borrow_mut() = false;
-    } else {
-        *is_playing_rc.borrow_mut() = true;
-    }
+    let state = *is_playing_rc.borrow();
+    *is_playing_rc.borrow_mut() = !state;
 }
 
-fn update(balls: &mut RefMut<Vec<Ball>>) {
-    let canvas = query_selector_to::<HtmlCanvasElement>("canvas").unwrap();
-    let ctx = canvas
-        .get_context("2d")
-        .unwrap()
-        .unwrap()
-        .dyn_into::<CanvasRenderingContext2d>()
-        .unwrap();
-
-    ctx.set_fill_style(&JsValue::from_str("rgba(0,0,0,1)"));
-    ctx.fill_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
-
-    let mut ballrefs: Vec<&Ball> = vec![];
-    for ball in balls.iter() {
-        ballrefs.push(ball.clone());
+fn update(world: &mut RefMut<World>, renderer: &Renderer, now: f64) {
+    //物理は経過時間に応じて決まった時間刻みで進める(1フレームで0回のことも複数回のこともある)
+    world.settings = collision_settings();
+    //選んでいる方式が変わったときだけ作り直す 同じ方式なら前のステップから持ち越したツリーや並び順をそのまま使う
+    if let Some(name) = selected_broad_phase() {
+        if world.broad_phase.name() != name {
+            if let Some(broad_phase) = broad_phase::from_name(&name) {
+                world.broad_phase = broad_phase;
+            }
+        }
     }
-
-    ctx.set_stroke_style(&JsValue::from_str("rgba(0.0,255.0,255.0,0.2)"));
-    ctx.set_line_width(2.0);
-
-    let _timer = Timer::new("create_tree");
-
-    let loot_node = create_tree(ballrefs.clone(), &ctx, false);
-
-    drop(_timer);
-
-    // let balls_with_possible_contact = get_contact_with(
-    //     loot_node.left_child.as_ref().unwrap(),
-    //     loot_node.right_child.as_ref().unwrap(),
-    //     Rc::new(RefCell::new(vec![])),
-    // );
-
-    let loot_node_box = Box::new(loot_node);
-    let balls_with_possible_contact: Rc<RefCell<Vec<(&Ball, &Ball)>>> =
-        Rc::new(RefCell::new(vec![]));
-
     {
-        let _timer = Timer::new("get_contact_with");
-        for ball in balls.iter() {
-            get_contact_with(&ball, &loot_node_box, balls_with_possible_contact.clone());
-        }
+        let _timer = Timer::new("physics");
+        world.advance(now);
     }
 
     log(&format!(
-        "collision_count:{:?}",
-        balls_with_possible_contact.borrow().len()
+        "broad_phase:{},collision_count:{:?},contact_count:{:?}",
+        world.broad_phase.name(),
+        world.pairs.len(),
+        world.contacts.len()
     ));
 
-    ctx.set_stroke_style(&JsValue::from_str("rgba(255.0,0.0,0.0,1)"));
-    ctx.set_line_width(4.0);
-
-    for balls in balls_with_possible_contact.borrow().iter() {
-        let aabb = Aabb::from_ballrefs(&vec![balls.0, balls.1]);
-        draw_aabb(&ctx, &aabb);
-    }
-
-    //ここで狭域当たり判定
-
-    for ball in balls.iter_mut() {
-        ball.draw(&ctx);
-        ball.moving(canvas.width() as f64, canvas.height() as f64);
-    }
-}
-
-//トップダウンでツリーすべてを走査する(先に作った方の)やつ
-fn get_contact_with_top_down<'a>(
-    node: &Box<Node<'a>>,
-    other: &Box<Node<'a>>,
-    balls_with_possible_contact: Rc<RefCell<Vec<(&'a Ball, &'a Ball)>>>,
-) -> Vec<(&'a Ball, &'a Ball)> {
-    if !node.aabb.is_intersects(&other.aabb) {
-        if node.balls.len() > 1 {
-            get_contact_with_top_down(
-                node.left_child.as_ref().unwrap(),
-                node.right_child.as_ref().unwrap(),
-                balls_with_possible_contact.clone(),
-            );
-        }
-        if other.balls.len() > 1 {
-            get_contact_with_top_down(
-                other.left_child.as_ref().unwrap(),
-                other.right_child.as_ref().unwrap(),
-                balls_with_possible_contact.clone(),
-            );
-        }
-
-        return balls_with_possible_contact.borrow().clone();
-    }
-
-    //接触
-    if node.balls.len() == 1 && other.balls.len() == 1 {
-        balls_with_possible_contact
-            .borrow_mut()
-            .push((node.balls[0], other.balls[0]));
-        // log(&format!(
-        //     "node:{:?},other:{:?},contact_list_ren:{}",
-        //     node.balls[0].name,
-        //     other.balls[0].name,
-        //     balls_with_possible_contact.borrow().len()
-        // ));
-
-        {
-            let canvas = query_selector_to::<HtmlCanvasElement>("canvas").unwrap();
-            let ctx = canvas
-                .get_context("2d")
-                .unwrap()
-                .unwrap()
-                .dyn_into::<CanvasRenderingContext2d>()
-                .unwrap();
-
-            node.balls[0].draw(&ctx);
-            other.balls[0].draw(&ctx);
-
-            ctx.set_stroke_style(&JsValue::from_str("rgba(255.0,0.0,0.0,1)"));
-            ctx.set_line_width(4.0);
-
-            draw_aabb(&ctx, &Aabb::from_aabbs(vec![node.aabb, other.aabb]));
-        }
-
-        return balls_with_possible_contact.borrow().clone();
-    }
-
-    if node.balls.len() > 1 && (other.balls.len() == 1 || (node.aabb.size() >= other.aabb.size())) {
-        let balls_with_possible_contact_clone = balls_with_possible_contact.clone();
-        let left_child_result = get_contact_with_top_down(
-            node.left_child.as_ref().unwrap(),
-            other,
-            balls_with_possible_contact_clone,
-        );
-
-        // balls_with_possible_contact
-        //     .borrow_mut()
-        //     .extend(left_child_result);
-
-        let balls_with_possible_contact_clone = balls_with_possible_contact.clone();
-
-        get_contact_with_top_down(
-            node.right_child.as_ref().unwrap(),
-            other,
-            balls_with_possible_contact_clone,
-        );
-    }
-
-    if other.balls.len() > 1 && (node.balls.len() == 1 || (node.aabb.size() < other.aabb.size())) {
-        let balls_with_possible_contact_clone = balls_with_possible_contact.clone();
-
-        let other_left_child_result = get_contact_with_top_down(
-            node,
-            other.left_child.as_ref().unwrap(),
-            balls_with_possible_contact_clone,
-        );
-
-        // balls_with_possible_contact
-        //     .borrow_mut()
-        //     .extend(other_left_child_result);
-
-        let balls_with_possible_contact_clone = balls_with_possible_contact.clone();
-
-        get_contact_with_top_down(
-            node,
-            other.right_child.as_ref().unwrap(),
-            balls_with_possible_contact_clone,
-        );
-    }
-
-    balls_with_possible_contact.borrow().clone()
-}
-
-fn get_contact_with<'a>(
-    ball: &'a Ball,
-    node: &Box<Node<'a>>,
-    balls_with_possible_contact: Rc<RefCell<Vec<(&'a Ball, &'a Ball)>>>,
-) {
-    let ball_aabb = Aabb::from_circle(ball.x, ball.y, ball.size);
-
-    //接触なし
-    if !ball_aabb.is_intersects(&node.aabb) {
-        return;
-    }
-
-    //接触
-    if node.balls.len() == 1 {
-        if node.balls[0].id <= ball.id {
-            return;
-        }
-
-        balls_with_possible_contact
-            .borrow_mut()
-            .push((ball, node.balls[0]));
-
-        return;
-    }
-    //リーフノードでない場合、再帰的にツリーを降下する
-    else if node.balls.len() > 1 {
-        get_contact_with(
-            ball,
-            node.left_child.as_ref().unwrap(),
-            balls_with_possible_contact.clone(),
-        );
-
-        get_contact_with(
-            ball,
-            node.right_child.as_ref().unwrap(),
-            balls_with_possible_contact,
-        );
-    }
-}
-fn create_tree<'a>(
-    balls: Vec<&'a Ball>,
-    ctx: &'a CanvasRenderingContext2d,
-    y_axis_division: bool,
-) -> Node<'a> {
-    let aabb = Aabb::from_ballrefs(&balls);
-    draw_aabb(&ctx, &aabb);
-    //オブジェクト数が1つのAABBはそれ以上分類できないので決め打ちで葉要素として最終処理
-    if balls.len() == 1 {
-        return Node {
-            left_child: None,
-            right_child: None,
-            balls,
-            aabb,
-        };
-    }
-
-    //中点をAABBから取る関係上自身の軸サイズと自身が所属するAABBの軸サイズが一致すると右にも左にも分類できないオブジェクトが発生する
-    //例えば小さいオブジェクトが大きいオブジェクトの影に隠れる(同一y軸にはいる)形になると、大きいオブジェクトのmax_x,min_xがAABB全体のmax_x,min_xになってしまう
-    //そうしたときに間違って両方を同じサイドのchildに入れてしまうと無限ループが発生する(再帰呼び出しした先でも同じサイドのchildに入れられる)
-    let mut left_balls: Vec<&Ball> = vec![];
-    let mut right_balls: Vec<&Ball> = vec![];
-    let mut center_balls: Vec<&Ball> = vec![];
-
-    //フラグで分割する軸を変更
-    if y_axis_division {
-        let parent_center_y = (aabb.y_max + aabb.y_min) / 2.0;
-
-        for ball in balls.iter() {
-            if ball.y < parent_center_y {
-                left_balls.push(ball.clone());
-            } else if ball.y > parent_center_y {
-                right_balls.push(ball.clone());
-            } else {
-                center_balls.push(ball.clone());
-            }
-        }
-    } else {
-        let parent_center_x = (aabb.x_max + aabb.x_min) / 2.0;
-        for ball in balls.iter() {
-            if ball.x < parent_center_x {
-                left_balls.push(ball.clone());
-            } else if ball.x > parent_center_x {
-                right_balls.push(ball.clone());
-            } else {
-                center_balls.push(ball.clone());
-            }
-        }
-    }
-
-    //分類できないオブジェクトは、左右のchildを見て少ない方に入れることでオブジェクト数が2つだけのAABBになった場合のループを回避する
-    for ball in center_balls {
-        if left_balls.len() <= right_balls.len() {
-            left_balls.push(ball);
-        } else {
-            right_balls.push(ball);
-        }
-    }
-
-    let mut left_child = None;
-    let mut right_child = None;
-
-    if left_balls.len() > 0 {
-        //次回の分割方向は今回とは別の軸を使う(!y_axis_division)
-        left_child = Some(Box::new(create_tree(left_balls, &ctx, !y_axis_division)));
-    }
-    if right_balls.len() > 0 {
-        right_child = Some(Box::new(create_tree(right_balls, &ctx, !y_axis_division)));
-    }
-
-    let node = Node {
-        left_child,
-        right_child,
-        balls,
-        aabb,
+    //方式ごとの絞り込み具合を比べられるように、ボール1個あたりに調べたAABBの数も出す
+    let overlap_tests = match world.broad_phase.average_overlap_tests() {
+        Some(overlap_tests) => format!("{:.1}", overlap_tests),
+        None => "-".to_string(),
     };
-
-    node
+    query_selector_to::<HtmlParagraphElement>(".collision-counter")
+        .unwrap()
+        .set_inner_html(&format!(
+            "pairs: {}, contacts: {}, overlap tests/query: {}",
+            world.pairs.len(),
+            world.contacts.len(),
+            overlap_tests
+        ));
+
+    renderer.draw(world);
 }
 
-struct Node<'a> {
-    left_child: Option<Box<Node<'a>>>,
-    right_child: Option<Box<Node<'a>>>,
-    balls: Vec<&'a Ball>,
-    aabb: Aabb,
+//広域当たり判定の方式も画面のセレクトボックスから毎フレーム読む
+fn selected_broad_phase() -> Option<String> {
+    query_selector_to::<HtmlSelectElement>(".broad-phase").map(|select| select.value())
 }
 
-fn draw_aabb(ctx: &CanvasRenderingContext2d, aabb: &Aabb) {
-    ctx.begin_path();
-
-    ctx.move_to(aabb.x_min, aabb.y_min);
-    ctx.line_to(aabb.x_max, aabb.y_min);
-    ctx.line_to(aabb.x_max, aabb.y_max);
-    ctx.line_to(aabb.x_min, aabb.y_max);
-    ctx.line_to(aabb.x_min, aabb.y_min);
+//反発係数と摩擦係数は画面の入力欄から毎フレーム読む 読めなければ既定値 範囲外の値はCollisionSettings::newが0〜1に収める
+fn collision_settings() -> CollisionSettings {
+    let read = |selector: &str| {
+        query_selector_to::<HtmlInputElement>(selector)
+            .and_then(|input| input.value().parse::<f64>().ok())
+    };
 
-    ctx.stroke();
+    let default = CollisionSettings::default();
+    CollisionSettings::new(
+        read(".restitution-field").unwrap_or(default.restitution),
+        read(".friction-field").unwrap_or(default.friction),
+    )
 }
 
-fn balls_init(balls_rc: &Rc<RefCell<Vec<Ball>>>, balls_size: i32) {
-    let canvas = query_selector_to::<HtmlCanvasElement>("canvas").unwrap();
-    balls_rc.borrow_mut().clear();
+fn balls_init(world_rc: &Rc<RefCell<World>>, balls_size: i32) {
+    let mut world = world_rc.borrow_mut();
+    let (width, height) = (world.width, world.height);
+    world.balls.clear();
 
     for i in 0..balls_size {
         let size = random_f64(5.0, 10.0);
         let ball = Ball::new(
-            random_f64(0.0 + size, canvas.width() as f64 - size),
-            random_f64(0.0 + size, canvas.height() as f64 - size),
-            random_f64(-2.0, 2.0),
-            random_f64(-2.0, 2.0),
+            random_f64(0.0 + size, width - size),
+            random_f64(0.0 + size, height - size),
+            //元は1フレームあたりの移動量だったので、60fpsで同じ速さになるように秒速に直す
+            random_f64(-2.0, 2.0) / TIMESTEP,
+            random_f64(-2.0, 2.0) / TIMESTEP,
             &random_rgb(),
             size,
             i,
         );
 
-        balls_rc.borrow_mut().push(ball);
+        world.balls.push(ball);
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-struct Aabb {
-    x_max: f64,
-    x_min: f64,
-    y_max: f64,
-    y_min: f64,
-}
-impl Aabb {
-    fn from_circle(x: f64, y: f64, size: f64) -> Aabb {
-        Aabb {
-            x_max: x + size,
-            x_min: x - size,
-            y_max: y + size,
-            y_min: y - size,
-        }
-    }
-
-    fn from_aabbs(aabbs: Vec<Aabb>) -> Aabb {
-        let mut x_max = -f64::INFINITY;
-        let mut x_min = f64::INFINITY;
-        let mut y_max = -f64::INFINITY;
-        let mut y_min = f64::INFINITY;
-
-        for aabb in aabbs {
-            if aabb.x_max > x_max {
-                x_max = aabb.x_max;
-            }
-            if aabb.x_min < x_min {
-                x_min = aabb.x_min;
-            }
-            if aabb.y_max > y_max {
-                y_max = aabb.y_max;
-            }
-            if aabb.y_min < y_min {
-                y_min = aabb.y_min
-            }
-        }
-
-        Aabb {
-            x_max,
-            x_min,
-            y_max,
-            y_min,
-        }
-    }
-
-    fn from_balls(balls: &Vec<Ball>) -> Aabb {
-        let mut x_max = -f64::INFINITY;
-        let mut x_min = f64::INFINITY;
-        let mut y_max = -f64::INFINITY;
-        let mut y_min = f64::INFINITY;
-
-        for ball in balls {
-            let aabb = ball.aabb();
-            if aabb.x_max > x_max {
-                x_max = aabb.x_max;
-            }
-            if aabb.x_min < x_min {
-                x_min = aabb.x_min;
-            }
-            if aabb.y_max > y_max {
-                y_max = aabb.y_max;
-            }
-            if aabb.y_min < y_min {
-                y_min = aabb.y_min
+//ベンチマーク(benches/broad_phase.rs)から広域当たり判定の方式を比べるための入り口 wasmからは使わない
+#[doc(hidden)]
+pub mod bench {
+    use crate::physics::broad_phase;
+    pub use crate::physics::broad_phase::NAMES;
+    use crate::physics::world::World;
+    use crate::physics::Ball;
+
+    //ベンチに置くボール1個分 速度はpx/s
+    #[derive(Clone, Copy, Debug)]
+    pub struct BallSpec {
+        pub x: f64,
+        pub y: f64,
+        pub vel_x: f64,
+        pub vel_y: f64,
+        pub size: f64,
+    }
+
+    //画面と同じWorldを、選んだ広域当たり判定で動かす
+    pub struct Scene {
+        world: World,
+    }
+
+    impl Scene {
+        //広域当たり判定の方式はNAMESのどれか
+        pub fn new(width: f64, height: f64, balls: &[BallSpec], broad_phase: &str) -> Scene {
+            let mut world = World::new(width, height);
+            world.broad_phase = broad_phase::from_name(broad_phase).unwrap();
+            for (i, spec) in balls.iter().enumerate() {
+                world.balls.push(Ball::new(
+                    spec.x,
+                    spec.y,
+                    spec.vel_x,
+                    spec.vel_y,
+                    "rgb(0,0,0)",
+                    spec.size,
+                    i as i32,
+                ));
             }
+            Scene { world }
         }
 
-        Aabb {
-            x_max,
-            x_min,
-            y_max,
-            y_min,
-        }
-    }
-    fn from_ballrefs(balls: &Vec<&Ball>) -> Aabb {
-        let mut x_max = -f64::INFINITY;
-        let mut x_min = f64::INFINITY;
-        let mut y_max = -f64::INFINITY;
-        let mut y_min = f64::INFINITY;
-
-        for ball in balls {
-            let aabb = ball.aabb();
-            if aabb.x_max > x_max {
-                x_max = aabb.x_max;
-            }
-            if aabb.x_min < x_min {
-                x_min = aabb.x_min;
-            }
-            if aabb.y_max > y_max {
-                y_max = aabb.y_max;
-            }
-            if aabb.y_min < y_min {
-                y_min = aabb.y_min
-            }
+        //World::stepで1ステップ進める 広域当たり判定のあとのCCDや接触の解決も画面と同じように走る
+        pub fn step(&mut self) {
+            self.world.step();
         }
 
-        Aabb {
-            x_max,
-            x_min,
-            y_max,
-            y_min,
+        //直近のstepで広域当たり判定に残ったペアの数
+        pub fn pairs(&self) -> usize {
+            self.world.pairs.len()
         }
-    }
 
-    fn is_intersects(&self, other: &Aabb) -> bool {
-        if self.x_min > other.x_max {
-            return false;
-        }
-        if self.x_max < other.x_min {
-            return false;
+        //直近のstepで、ボール1個あたりに調べたAABBの重なりの数
+        pub fn average_overlap_tests(&self) -> Option<f64> {
+            self.world.broad_phase.average_overlap_tests()
         }
-        if self.y_min > other.y_max {
-            return false;
-        }
-        if self.y_max < other.y_min {
-            return false;
-        }
-
-        return true;
-    }
-
-    fn size(&self) -> f64 {
-        let x_size = self.x_max - self.x_min;
-        let y_size = self.y_max - self.y_min;
-
-        x_size + y_size
-    }
-}
-#[derive(Debug)]
-struct Ball {
-    x: f64,
-    y: f64,
-    vel_x: f64,
-    vel_y: f64,
-    color: String,
-    size: f64,
-    id: i32,
-}
-impl Ball {
-    fn new(x: f64, y: f64, vel_x: f64, vel_y: f64, color: &str, size: f64, id: i32) -> Ball {
-        Ball {
-            x,
-            y,
-            vel_x,
-            vel_y,
-            color: color.to_string(),
-            size,
-            id,
-        }
-    }
-    fn draw(&self, ctx: &CanvasRenderingContext2d) {
-        ctx.begin_path();
-        ctx.set_fill_style(&JsValue::from_str(&self.color));
-        ctx.arc(self.x, self.y, self.size, 0.0, 2.0 * std::f64::consts::PI)
-            .unwrap();
-        ctx.fill();
-    }
-
-    fn moving(&mut self, canvas_width: f64, canvas_height: f64) {
-        if self.x + self.size >= canvas_width {
-            self.vel_x = -self.vel_x;
-        }
-        if self.x - self.size <= 0.0 {
-            self.vel_x = -self.vel_x;
-        }
-        if self.y + self.size >= canvas_height {
-            self.vel_y = -self.vel_y;
-        }
-        if self.y - self.size <= 0.0 {
-            self.vel_y = -self.vel_y;
-        }
-        self.x += self.vel_x;
-        self.y += self.vel_y;
-    }
-
-    fn aabb(&self) -> Aabb {
-        Aabb::from_circle(self.x, self.y, self.size)
     }
 }
 
 fn random_f64(min: f64, max: f64) -> f64 {
-    Math::floor(Math::random() * (max - min + 1.0) as f64) + min
+    Math::floor(Math::random() * (max - min + 1.0)) + min
 }
 fn random_rgb() -> String {
     format!(
@@ -639,7 +281,7 @@ fn random_rgb() -> String {
     )
 }
 
-fn request_animation_frame(closure_rc: &Rc<RefCell<Option<Closure<dyn FnMut()>>>>) -> i32 {
+fn request_animation_frame(closure_rc: &FrameClosure) -> i32 {
     web_sys::window()
         .unwrap()
         .request_animation_frame(
@@ -698,7 +340,7 @@ impl Fps {
 
         let mean = sum / self.frames.len() as f64;
         self.body.set_inner_html(
-            &format!(
+            format!(
                 "fps counter<br>latest = {}<br>avg of last 100 = {}<br>min of last 100 = {}<br>max of last 100 = {}",
                 Math::round(fps),
                 Math::round(mean),
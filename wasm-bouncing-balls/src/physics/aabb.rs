@@ -0,0 +1,105 @@
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub x_max: f64,
+    pub x_min: f64,
+    pub y_max: f64,
+    pub y_min: f64,
+}
+impl Aabb {
+    pub fn from_circle(x: f64, y: f64, size: f64) -> Aabb {
+        Aabb {
+            x_max: x + size,
+            x_min: x - size,
+            y_max: y + size,
+            y_min: y - size,
+        }
+    }
+
+    //円がこのステップで(motion_x,motion_y)だけ動くとき、動き始めと動き終わりの両方を含むAABB
+    //途中で別のボールをすり抜けてしまうペアも広域当たり判定で拾えるようにする
+    pub fn from_swept_circle(x: f64, y: f64, size: f64, motion_x: f64, motion_y: f64) -> Aabb {
+        let aabb = Aabb::from_circle(x, y, size);
+        Aabb {
+            x_max: aabb.x_max + motion_x.max(0.0),
+            x_min: aabb.x_min + motion_x.min(0.0),
+            y_max: aabb.y_max + motion_y.max(0.0),
+            y_min: aabb.y_min + motion_y.min(0.0),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            x_max: self.x_max.max(other.x_max),
+            x_min: self.x_min.min(other.x_min),
+            y_max: self.y_max.max(other.y_max),
+            y_min: self.y_min.min(other.y_min),
+        }
+    }
+
+    pub fn is_intersects(&self, other: &Aabb) -> bool {
+        if self.x_min > other.x_max {
+            return false;
+        }
+        if self.x_max < other.x_min {
+            return false;
+        }
+        if self.y_min > other.y_max {
+            return false;
+        }
+        if self.y_max < other.y_min {
+            return false;
+        }
+
+        true
+    }
+
+    pub fn size(&self) -> f64 {
+        let x_size = self.x_max - self.x_min;
+        let y_size = self.y_max - self.y_min;
+
+        x_size + y_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Ball;
+
+    //動く向きにだけ伸びる
+    #[test]
+    fn swept_circle_extends_in_motion_direction() {
+        let aabb = Aabb::from_swept_circle(100.0, 50.0, 5.0, 20.0, -10.0);
+        assert_eq!(
+            (aabb.x_min, aabb.x_max, aabb.y_min, aabb.y_max),
+            (95.0, 125.0, 35.0, 55.0)
+        );
+    }
+
+    //辺が接しているだけでも重なっているとみなす
+    #[test]
+    fn touching_aabbs_intersect() {
+        let a = Aabb::from_circle(0.0, 0.0, 5.0);
+        assert!(a.is_intersects(&Aabb::from_circle(10.0, 0.0, 5.0)));
+        assert!(a.is_intersects(&Aabb::from_circle(10.0, 10.0, 5.0)));
+        assert!(!a.is_intersects(&Aabb::from_circle(10.1, 0.0, 5.0)));
+        assert!(!a.is_intersects(&Aabb::from_circle(0.0, -10.1, 5.0)));
+    }
+
+    #[test]
+    fn union_and_size() {
+        let a = Aabb::from_circle(0.0, 0.0, 5.0);
+        let b = Aabb::from_circle(20.0, 10.0, 5.0);
+        let union = a.union(&b);
+        assert_eq!(
+            (union.x_min, union.x_max, union.y_min, union.y_max),
+            (-5.0, 25.0, -5.0, 15.0)
+        );
+        assert_eq!(union.size(), 50.0);
+
+        let a = Ball::new(0.0, 0.0, 0.0, 0.0, "rgb(0,0,0)", 5.0, 0);
+        let b = Ball::new(20.0, 10.0, 0.0, 0.0, "rgb(0,0,0)", 5.0, 1);
+        let from_balls = a.aabb().union(&b.aabb());
+        assert_eq!(from_balls.size(), union.size());
+    }
+}
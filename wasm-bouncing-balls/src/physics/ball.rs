@@ -0,0 +1,103 @@
+use super::Aabb;
+
+#[derive(Debug)]
+pub struct Ball {
+    pub x: f64,
+    pub y: f64,
+    //1つ前のステップの位置 描画の補間に使う
+    pub prev_x: f64,
+    pub prev_y: f64,
+    //速度(px/s)
+    pub vel_x: f64,
+    pub vel_y: f64,
+    //このステップで動く予定の量 広域当たり判定のAABBをこの分だけ伸ばす(ステップの外では0)
+    pub motion_x: f64,
+    pub motion_y: f64,
+    pub color: String,
+    pub size: f64,
+    pub id: i32,
+}
+impl Ball {
+    pub fn new(x: f64, y: f64, vel_x: f64, vel_y: f64, color: &str, size: f64, id: i32) -> Ball {
+        Ball {
+            x,
+            y,
+            prev_x: x,
+            prev_y: y,
+            vel_x,
+            vel_y,
+            motion_x: 0.0,
+            motion_y: 0.0,
+            color: color.to_string(),
+            size,
+            id,
+        }
+    }
+    //今の速度のままtime秒進める
+    pub fn move_by(&mut self, time: f64) {
+        self.x += self.vel_x * time;
+        self.y += self.vel_y * time;
+    }
+
+    //壁にめり込んでいたら壁の内側に戻して、壁に向かう向きの速度だけ反転させる
+    //壁との衝突はccdで衝突時刻に跳ね返すので、ここに来るのは最初からめり込んでいたときか衝突の回数が上限を超えたときだけ
+    pub fn keep_inside(&mut self, canvas_width: f64, canvas_height: f64) {
+        if self.x + self.size >= canvas_width {
+            self.x = canvas_width - self.size;
+            self.vel_x = -self.vel_x.abs();
+        }
+        if self.x - self.size <= 0.0 {
+            self.x = self.size;
+            self.vel_x = self.vel_x.abs();
+        }
+        if self.y + self.size >= canvas_height {
+            self.y = canvas_height - self.size;
+            self.vel_y = -self.vel_y.abs();
+        }
+        if self.y - self.size <= 0.0 {
+            self.y = self.size;
+            self.vel_y = self.vel_y.abs();
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_swept_circle(self.x, self.y, self.size, self.motion_x, self.motion_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_by_uses_velocity_in_pixels_per_second() {
+        let mut ball = Ball::new(100.0, 100.0, 60.0, -30.0, "rgb(0,0,0)", 5.0, 0);
+        ball.move_by(0.5);
+        assert_eq!((ball.x, ball.y), (130.0, 85.0));
+        //描画の補間に使う前の位置はステップの始めにWorldが入れるので、ここでは変わらない
+        assert_eq!((ball.prev_x, ball.prev_y), (100.0, 100.0));
+    }
+
+    //壁の外に出ていたら内側に戻し、壁から離れる向きの速度にする
+    #[test]
+    fn keep_inside_pushes_back_and_reflects() {
+        let mut ball = Ball::new(198.0, 3.0, 50.0, -50.0, "rgb(0,0,0)", 5.0, 0);
+        ball.keep_inside(200.0, 100.0);
+        assert_eq!((ball.x, ball.y), (195.0, 5.0));
+        assert_eq!((ball.vel_x, ball.vel_y), (-50.0, 50.0));
+
+        //すでに離れる向きに動いていれば速度はそのまま
+        let mut ball = Ball::new(2.0, 50.0, 50.0, 0.0, "rgb(0,0,0)", 5.0, 0);
+        ball.keep_inside(200.0, 100.0);
+        assert_eq!((ball.x, ball.vel_x), (5.0, 50.0));
+    }
+
+    #[test]
+    fn aabb_includes_planned_motion() {
+        let mut ball = Ball::new(100.0, 100.0, 0.0, 0.0, "rgb(0,0,0)", 5.0, 0);
+        ball.motion_x = -8.0;
+        let aabb = ball.aabb();
+        assert_eq!((aabb.x_min, aabb.x_max), (87.0, 105.0));
+        assert_eq!((aabb.y_min, aabb.y_max), (95.0, 105.0));
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+
+use super::bvh::{Bvh, BvhBuilder, BvhTraversal};
+use super::dynamic_tree::DynamicTree;
+use super::{Aabb, Ball};
+
+//広域当たり判定の方式をそろえて扱うためのトレイト 画面のセレクトボックスで切り替えて比べる
+//どの方式もボールのAABB(動く予定の分を含む)が重なるペアだけをちょうど返すので、結果は総当たりと同じになる
+//ボールのidはballs上の位置と同じ前提で、返したidはそのままballsの添字に使われる(World::stepで確かめている)
+pub trait BroadPhase {
+    //セレクトボックスの値と同じ名前
+    fn name(&self) -> &'static str;
+
+    //AABBが重なっているボールのペアをidで集める 各ペアは1回ずつ(idの小さい方が先)で、順番は決まっていない
+    fn pairs(&mut self, balls: &[Ball]) -> Vec<(i32, i32)>;
+
+    //デバッグ表示用に、直近のpairsで使ったAABB(ツリーのノードやグリッドのセル)を1つずつ渡す
+    fn for_each_aabb(&self, _callback: &mut dyn FnMut(&Aabb)) {}
+
+    //直近のpairsで、ボール1個あたりに調べたAABBの重なりの数 数えていない方式はNone
+    fn average_overlap_tests(&self) -> Option<f64> {
+        None
+    }
+}
+
+//選べる方式の名前
+pub const NAMES: [&str; 7] = [
+    "dynamic",
+    "midpoint",
+    "sah",
+    "midpoint-dual",
+    "sah-dual",
+    "grid",
+    "sap",
+];
+
+//画面のセレクトボックスの値から
+pub fn from_name(name: &str) -> Option<Box<dyn BroadPhase>> {
+    match name {
+        "dynamic" => Some(Box::new(Dynamic::new())),
+        "grid" => Some(Box::new(SpatialHash::new())),
+        "sap" => Some(Box::new(SweepAndPrune::new())),
+        _ => Bvh::from_name(name).map(|bvh| Box::new(bvh) as Box<dyn BroadPhase>),
+    }
+}
+
+//毎回作り直すBVH
+impl BroadPhase for Bvh {
+    fn name(&self) -> &'static str {
+        match (self.builder, self.traversal) {
+            (BvhBuilder::Midpoint, BvhTraversal::PerBall) => "midpoint",
+            (BvhBuilder::Sah, BvhTraversal::PerBall) => "sah",
+            (BvhBuilder::Midpoint, BvhTraversal::DualTree) => "midpoint-dual",
+            (BvhBuilder::Sah, BvhTraversal::DualTree) => "sah-dual",
+        }
+    }
+
+    fn pairs(&mut self, balls: &[Ball]) -> Vec<(i32, i32)> {
+        super::bvh::broad_phase(balls, self)
+            .iter()
+            .map(|(a, b)| (a.id, b.id))
+            .collect()
+    }
+
+    fn for_each_aabb(&self, callback: &mut dyn FnMut(&Aabb)) {
+        for node in self.nodes.iter() {
+            callback(&node.aabb);
+        }
+    }
+
+    fn average_overlap_tests(&self) -> Option<f64> {
+        Some(Bvh::average_overlap_tests(self))
+    }
+}
+
+//ステップをまたいで持ち続ける動的ツリー
+pub struct Dynamic {
+    pub tree: DynamicTree,
+    //ボールのidと同じ番号に、そのボールのツリーのプロキシ
+    proxies: Vec<usize>,
+}
+
+impl Dynamic {
+    pub fn new() -> Dynamic {
+        Dynamic {
+            tree: DynamicTree::new(),
+            proxies: vec![],
+        }
+    }
+
+    //ボールの今のAABB(動く予定の分を含む)をツリーに反映する fat AABBからはみ出したボールだけ入れ直される
+    pub fn update(&mut self, balls: &[Ball]) {
+        //balls_initでボールを入れ直したときなどは、プロキシの数をボールの数に合わせる 位置が飛んだ分はmove_proxyで入れ直される
+        while self.proxies.len() > balls.len() {
+            let proxy = self.proxies.pop().unwrap();
+            self.tree.destroy_proxy(proxy);
+        }
+        for ball in balls[self.proxies.len()..].iter() {
+            self.proxies
+                .push(self.tree.create_proxy(&ball.aabb(), ball.id));
+        }
+
+        for (ball, &proxy) in balls.iter().zip(self.proxies.iter()) {
+            self.tree
+                .move_proxy(proxy, &ball.aabb(), ball.motion_x, ball.motion_y);
+        }
+    }
+}
+
+impl BroadPhase for Dynamic {
+    fn name(&self) -> &'static str {
+        "dynamic"
+    }
+
+    //ツリーの葉はfat AABBなので、実際のAABBが重なっていないペアはここで落とす
+    fn pairs(&mut self, balls: &[Ball]) -> Vec<(i32, i32)> {
+        self.update(balls);
+        let mut pairs = self.tree.pairs();
+        pairs.retain(|&(a, b)| {
+            balls[a as usize]
+                .aabb()
+                .is_intersects(&balls[b as usize].aabb())
+        });
+        pairs
+    }
+
+    fn for_each_aabb(&self, callback: &mut dyn FnMut(&Aabb)) {
+        self.tree.for_each_aabb(|aabb, _| callback(aabb));
+    }
+}
+
+//一様グリッド(空間ハッシュ) ほとんどのボールのAABBが入る大きさのセルに区切り、ボールを重なるセルすべてに入れる
+//同じセルに入ったボール同士だけを調べる セルはHashMapで持つので、画面の広さに関係なく使ったセルの分だけで済む
+#[derive(Default)]
+pub struct SpatialHash {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    aabbs: Vec<Aabb>,
+    //セルの大きさを決めるための、AABBの長い方の辺の長さ 毎回使い回す
+    extents: Vec<f64>,
+    queries: usize,
+    overlap_tests: usize,
+}
+
+//AABBの辺の長さのこの割合の位置(0.9なら短い方から9割)をセルの大きさにする
+const CELL_SIZE_PERCENTILE: f64 = 0.9;
+
+impl SpatialHash {
+    pub fn new() -> SpatialHash {
+        SpatialHash::default()
+    }
+
+    fn cell_of(&self, x: f64, y: f64) -> (i64, i64) {
+        (
+            (x / self.cell_size).floor() as i64,
+            (y / self.cell_size).floor() as i64,
+        )
+    }
+}
+
+impl BroadPhase for SpatialHash {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn pairs(&mut self, balls: &[Ball]) -> Vec<(i32, i32)> {
+        self.aabbs.clear();
+        self.aabbs.extend(balls.iter().map(|ball| ball.aabb()));
+        //一番大きいAABBに合わせると、速いボールが1つあるだけでセルが粗くなって全体が遅くなる
+        //ほとんどのAABBが1セルに収まる大きさにして、それより大きいAABBは重なるセルすべてに入れる
+        self.extents.clear();
+        self.extents.extend(
+            self.aabbs
+                .iter()
+                .map(|aabb| (aabb.x_max - aabb.x_min).max(aabb.y_max - aabb.y_min)),
+        );
+        self.cell_size = if self.extents.is_empty() {
+            1.0
+        } else {
+            let k = ((self.extents.len() - 1) as f64 * CELL_SIZE_PERCENTILE) as usize;
+            let (_, &mut extent, _) = self
+                .extents
+                .select_nth_unstable_by(k, |a, b| a.partial_cmp(b).unwrap());
+            extent.max(1.0)
+        };
+
+        //前のフレームで使わなかったセルは捨て、使ったセルはVecを使い回す
+        self.cells.retain(|_, cell| !cell.is_empty());
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        for (i, aabb) in self.aabbs.iter().enumerate() {
+            let (x_min, y_min) = self.cell_of(aabb.x_min, aabb.y_min);
+            let (x_max, y_max) = self.cell_of(aabb.x_max, aabb.y_max);
+            for x in x_min..=x_max {
+                for y in y_min..=y_max {
+                    self.cells.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+
+        let mut pairs = vec![];
+        let mut overlap_tests = 0;
+        for (&cell, indices) in self.cells.iter() {
+            for (k, &i) in indices.iter().enumerate() {
+                for &j in indices[k + 1..].iter() {
+                    overlap_tests += 1;
+                    let (a, b) = (&self.aabbs[i], &self.aabbs[j]);
+                    if !a.is_intersects(b) {
+                        continue;
+                    }
+                    //2つとも入っているセルが複数あると同じペアが何度も見つかるので、重なっている部分の左上が入るセルでだけ数える
+                    if self.cell_of(a.x_min.max(b.x_min), a.y_min.max(b.y_min)) != cell {
+                        continue;
+                    }
+                    let (a, b) = (balls[i].id, balls[j].id);
+                    pairs.push((a.min(b), a.max(b)));
+                }
+            }
+        }
+        self.queries = balls.len();
+        self.overlap_tests = overlap_tests;
+
+        pairs
+    }
+
+    fn for_each_aabb(&self, callback: &mut dyn FnMut(&Aabb)) {
+        for (&(x, y), cell) in self.cells.iter() {
+            if cell.is_empty() {
+                continue;
+            }
+            callback(&Aabb {
+                x_max: (x + 1) as f64 * self.cell_size,
+                x_min: x as f64 * self.cell_size,
+                y_max: (y + 1) as f64 * self.cell_size,
+                y_min: y as f64 * self.cell_size,
+            });
+        }
+    }
+
+    fn average_overlap_tests(&self) -> Option<f64> {
+        Some(average(self.overlap_tests, self.queries))
+    }
+}
+
+//Sort and Sweep(Sweep and Prune) ボールをAABBの左端の順に並べ、左から順に右端までに始まるボールとだけ調べる
+//並び順は次のフレームに持ち越すので、ほとんど並んだままの列を並べ直すだけで済む
+#[derive(Default)]
+pub struct SweepAndPrune {
+    //ボールの番号(balls上の位置)をAABBの左端の順に並べたもの
+    order: Vec<usize>,
+    aabbs: Vec<Aabb>,
+    queries: usize,
+    overlap_tests: usize,
+}
+
+impl SweepAndPrune {
+    pub fn new() -> SweepAndPrune {
+        SweepAndPrune::default()
+    }
+}
+
+impl BroadPhase for SweepAndPrune {
+    fn name(&self) -> &'static str {
+        "sap"
+    }
+
+    fn pairs(&mut self, balls: &[Ball]) -> Vec<(i32, i32)> {
+        self.aabbs.clear();
+        self.aabbs.extend(balls.iter().map(|ball| ball.aabb()));
+        //ボールの数が変わったら並び順を作り直す
+        if self.order.len() != balls.len() {
+            self.order.clear();
+            self.order.extend(0..balls.len());
+        }
+        //標準のsortは並んでいる区間をそのまま使うので、ほとんど並んだままなら線形に近い時間で済む
+        let aabbs = &self.aabbs;
+        self.order
+            .sort_by(|&a, &b| aabbs[a].x_min.partial_cmp(&aabbs[b].x_min).unwrap());
+
+        let mut pairs = vec![];
+        let mut overlap_tests = 0;
+        for (k, &i) in self.order.iter().enumerate() {
+            let a = &aabbs[i];
+            for &j in self.order[k + 1..].iter() {
+                let b = &aabbs[j];
+                //ここから先のボールは左端がaの右端より右にあるので、x軸で重ならない
+                if b.x_min > a.x_max {
+                    break;
+                }
+                overlap_tests += 1;
+                if b.y_min > a.y_max || a.y_min > b.y_max {
+                    continue;
+                }
+                let (a, b) = (balls[i].id, balls[j].id);
+                pairs.push((a.min(b), a.max(b)));
+            }
+        }
+        self.queries = balls.len();
+        self.overlap_tests = overlap_tests;
+
+        pairs
+    }
+
+    fn average_overlap_tests(&self) -> Option<f64> {
+        Some(average(self.overlap_tests, self.queries))
+    }
+}
+
+fn average(overlap_tests: usize, queries: usize) -> f64 {
+    if queries == 0 {
+        return 0.0;
+    }
+    overlap_tests as f64 / queries as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //どの方式とも比べられるように、AABBが重なるペアを全部調べて集める
+    fn brute_force(balls: &[Ball]) -> Vec<(i32, i32)> {
+        let mut pairs = vec![];
+        for (i, a) in balls.iter().enumerate() {
+            for b in balls[i + 1..].iter() {
+                if a.aabb().is_intersects(&b.aabb()) {
+                    pairs.push((a.id.min(b.id), a.id.max(b.id)));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+
+    fn scattered_balls(count: i32) -> Vec<Ball> {
+        (0..count)
+            .map(|i| {
+                let mut ball = Ball::new(
+                    (i * 37 % 193) as f64 * 2.0,
+                    (i * 91 % 151) as f64 * 2.0,
+                    0.0,
+                    0.0,
+                    "rgb(0,0,0)",
+                    3.0 + (i % 5) as f64,
+                    i,
+                );
+                ball.motion_x = (i % 9) as f64 * 3.0 - 12.0;
+                ball.motion_y = (i % 7) as f64 * 4.0 - 12.0;
+                ball
+            })
+            .collect()
+    }
+
+    fn sorted_pairs(broad_phase: &mut dyn BroadPhase, balls: &[Ball]) -> Vec<(i32, i32)> {
+        let mut pairs = broad_phase.pairs(balls);
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for name in NAMES.iter() {
+            assert_eq!(from_name(name).unwrap().name(), *name);
+        }
+        assert!(from_name("unknown").is_none());
+    }
+
+    //どの方式も総当たりとまったく同じペアを返す 動かしながら何フレームか続けても変わらない
+    #[test]
+    fn all_broad_phases_match_brute_force() {
+        let mut broad_phases: Vec<Box<dyn BroadPhase>> =
+            NAMES.iter().map(|name| from_name(name).unwrap()).collect();
+        let mut balls = scattered_balls(300);
+        for frame in 0..5 {
+            for ball in balls.iter_mut() {
+                ball.x += ball.motion_x;
+                ball.y += ball.motion_y;
+            }
+            let expected = brute_force(&balls);
+            assert!(!expected.is_empty());
+            for broad_phase in broad_phases.iter_mut() {
+                assert_eq!(
+                    sorted_pairs(broad_phase.as_mut(), &balls),
+                    expected,
+                    "{} frame {}",
+                    broad_phase.name(),
+                    frame
+                );
+            }
+        }
+    }
+
+    //ボールの数が変わったり、同じ位置に重なっていたり、負の座標にはみ出していても総当たりと同じ
+    #[test]
+    fn all_broad_phases_handle_edge_cases() {
+        let stacked = || -> Vec<Ball> {
+            (0..10)
+                .map(|i| Ball::new(-3.0, 50.0, 0.0, 0.0, "rgb(0,0,0)", 5.0, i))
+                .collect()
+        };
+        for name in NAMES.iter() {
+            let mut broad_phase = from_name(name).unwrap();
+            for balls in [scattered_balls(50), vec![], stacked(), scattered_balls(1)].iter() {
+                assert_eq!(
+                    sorted_pairs(broad_phase.as_mut(), balls),
+                    brute_force(balls),
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+
+    //速いボールが1つ混じっていてもセルは小さいままで、大きいAABBは複数のセルにまたがって入る
+    #[test]
+    fn grid_cell_size_ignores_outliers() {
+        let mut balls = scattered_balls(100);
+        balls[0].motion_x = 300.0;
+        balls[0].motion_y = -200.0;
+
+        let mut grid = SpatialHash::new();
+        let mut pairs = grid.pairs(&balls);
+        pairs.sort_unstable();
+        assert_eq!(pairs, brute_force(&balls));
+        assert!(grid.cell_size < 50.0, "{}", grid.cell_size);
+        assert!(grid.cells.values().filter(|cell| cell.contains(&0)).count() > 4);
+    }
+
+    //グリッドとSort and Sweepは、総当たりよりずっと少ない数しか調べない
+    #[test]
+    fn grid_and_sap_prune_most_tests() {
+        let balls = scattered_balls(300);
+        for name in ["grid", "sap"].iter() {
+            let mut broad_phase = from_name(name).unwrap();
+            broad_phase.pairs(&balls);
+            let tests = broad_phase.average_overlap_tests().unwrap();
+            assert!(tests < 299.0 / 4.0, "{} {}", name, tests);
+        }
+    }
+}
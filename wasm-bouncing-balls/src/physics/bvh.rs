@@ -0,0 +1,715 @@
+use super::{Aabb, Ball};
+
+//毎回作り直すAABBツリー(BVH)
+//ノードは1本のVecに並べて子を番号で指す ボールはノードごとに持たず、ボールの番号を並べ替えた1本の配列の範囲で表す
+//分割するときにその範囲の中で並べ替えるので、どのノードのボールも配列の中で連続している
+//Vecは作り直しても捨てずに使い回すので、2回目以降はほとんどアロケーションしない
+
+//葉に入れるボールの数の上限
+const LEAF_SIZE: usize = 4;
+//SAHで分割位置の候補を作るときの区間(ビン)の数
+const SAH_BINS: usize = 16;
+
+//ノードをどこで2つに分けるか
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BvhBuilder {
+    //AABBの中点で分ける 段ごとにx軸とy軸を交互に使う
+    Midpoint,
+    //ボールの中心をビンに振り分け、ビンの境目のうち表面積ヒューリスティック(SAH)のコストが一番小さいところで分ける
+    //コストは子のAABBの大きさ×子に入るボールの数の和 2次元なのでAABBの大きさには周長(の半分)を使う
+    Sah,
+}
+
+impl BvhBuilder {
+    //画面のセレクトボックスの値から
+    pub fn from_name(name: &str) -> Option<BvhBuilder> {
+        match name {
+            "midpoint" => Some(BvhBuilder::Midpoint),
+            "sah" => Some(BvhBuilder::Sah),
+            _ => None,
+        }
+    }
+}
+
+//作った木をどうたどってペアを集めるか
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BvhTraversal {
+    //ボールごとに根から問い合わせる
+    PerBall,
+    //木と木を重ねて同時に降りていく 重ならない部分木どうしは中のボールを1つも調べずに済む
+    DualTree,
+}
+
+#[derive(Debug)]
+pub struct BvhNode {
+    pub aabb: Aabb,
+    //左右の子ノードの番号 葉ならNone
+    pub children: Option<(usize, usize)>,
+    //このノードに入っているボールはindices[start..end]
+    pub start: usize,
+    pub end: usize,
+}
+
+pub struct Bvh {
+    pub builder: BvhBuilder,
+    pub traversal: BvhTraversal,
+    //根は0番
+    pub nodes: Vec<BvhNode>,
+    //ボールの番号(balls上の位置)を、各ノードのボールが連続するように並べ替えたもの
+    pub indices: Vec<usize>,
+    //直近の広域当たり判定で木に問い合わせた回数と、そのときに調べたAABBの重なりの数 木の良さを比べるのに使う
+    pub queries: usize,
+    pub overlap_tests: usize,
+}
+
+impl Default for Bvh {
+    fn default() -> Bvh {
+        Bvh::with_builder(BvhBuilder::Midpoint)
+    }
+}
+
+impl Bvh {
+    pub fn with_builder(builder: BvhBuilder) -> Bvh {
+        Bvh {
+            builder,
+            traversal: BvhTraversal::PerBall,
+            nodes: vec![],
+            indices: vec![],
+            queries: 0,
+            overlap_tests: 0,
+        }
+    }
+
+    //画面のセレクトボックスの値から 後ろに"-dual"が付いていれば木どうしでたどる
+    pub fn from_name(name: &str) -> Option<Bvh> {
+        let (builder, traversal) = match name.strip_suffix("-dual") {
+            Some(builder) => (builder, BvhTraversal::DualTree),
+            None => (name, BvhTraversal::PerBall),
+        };
+        BvhBuilder::from_name(builder).map(|builder| Bvh {
+            traversal,
+            ..Bvh::with_builder(builder)
+        })
+    }
+
+    //1回の問い合わせあたりに調べたAABBの重なりの数 少ないほど無駄なく絞り込めている
+    pub fn average_overlap_tests(&self) -> f64 {
+        if self.queries == 0 {
+            return 0.0;
+        }
+        self.overlap_tests as f64 / self.queries as f64
+    }
+
+    pub fn build(&mut self, balls: &[Ball]) {
+        self.nodes.clear();
+        self.indices.clear();
+        self.indices.extend(0..balls.len());
+        self.queries = 0;
+        self.overlap_tests = 0;
+        if !balls.is_empty() {
+            self.build_node(balls, 0, balls.len(), false);
+        }
+    }
+
+    pub fn root(&self) -> Option<usize> {
+        if self.nodes.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+
+    //ノードに入っているボールの番号
+    pub fn balls_in(&self, node: usize) -> &[usize] {
+        let node = &self.nodes[node];
+        &self.indices[node.start..node.end]
+    }
+
+    //indices[start..end]のボールでノードを作り、番号を返す
+    //AABBの中点より前か後ろかで分けて、次の段では別の軸で分ける
+    fn build_node(
+        &mut self,
+        balls: &[Ball],
+        start: usize,
+        end: usize,
+        y_axis_division: bool,
+    ) -> usize {
+        let mut aabb = balls[self.indices[start]].aabb();
+        for &i in self.indices[start + 1..end].iter() {
+            aabb = aabb.union(&balls[i].aabb());
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            aabb,
+            children: None,
+            start,
+            end,
+        });
+        if end - start <= LEAF_SIZE {
+            return index;
+        }
+
+        let left = match self.builder {
+            BvhBuilder::Midpoint => {
+                midpoint_split(balls, &aabb, &mut self.indices[start..end], y_axis_division)
+            }
+            BvhBuilder::Sah => sah_split(balls, &mut self.indices[start..end]),
+        };
+
+        let middle = start + left;
+        let left_child = self.build_node(balls, start, middle, !y_axis_division);
+        let right_child = self.build_node(balls, middle, end, !y_axis_division);
+        self.nodes[index].children = Some((left_child, right_child));
+        index
+    }
+}
+
+//rangeのボールを左に入れるものが前に来るように並べ替えて、左に入れる数を返す(1以上range.len()未満)
+fn midpoint_split(
+    balls: &[Ball],
+    aabb: &Aabb,
+    range: &mut [usize],
+    y_axis_division: bool,
+) -> usize {
+    let coordinate = |ball: &Ball| if y_axis_division { ball.y } else { ball.x };
+    let center = if y_axis_division {
+        (aabb.y_max + aabb.y_min) / 2.0
+    } else {
+        (aabb.x_max + aabb.x_min) / 2.0
+    };
+
+    //中点より前・ちょうど中点・後ろの3つに並べ替える
+    let (mut less, mut i, mut greater) = (0, 0, range.len());
+    while i < greater {
+        let value = coordinate(&balls[range[i]]);
+        if value < center {
+            range.swap(less, i);
+            less += 1;
+            i += 1;
+        } else if value > center {
+            greater -= 1;
+            range.swap(i, greater);
+        } else {
+            i += 1;
+        }
+    }
+
+    //ちょうど中点にあるボールは左右の少ない方に入れていく(中点の並びは連続しているので、境目をずらすだけでいい)
+    let (mut left, mut right) = (less, range.len() - greater);
+    for _ in less..greater {
+        if left <= right {
+            left += 1;
+        } else {
+            right += 1;
+        }
+    }
+
+    //AABBを動く予定の分だけ伸ばしていると、中心がすべて中点の片側に寄ることがある
+    //そのままだと同じボールの組で再帰し続けるので、分割する軸の座標順に半分ずつに分ける
+    if left == 0 || right == 0 {
+        return median_split(balls, range, y_axis_division);
+    }
+    left
+}
+
+//分割する軸の座標順に並べて半分ずつに分ける
+fn median_split(balls: &[Ball], range: &mut [usize], y_axis_division: bool) -> usize {
+    let coordinate = |ball: &Ball| if y_axis_division { ball.y } else { ball.x };
+    range.sort_by(|&a, &b| {
+        coordinate(&balls[a])
+            .partial_cmp(&coordinate(&balls[b]))
+            .unwrap()
+    });
+    range.len() / 2
+}
+
+fn sah_split(balls: &[Ball], range: &mut [usize]) -> usize {
+    //ボールの中心が散らばっている範囲 ビンはこれを等分して作る
+    let mut centers = Aabb::from_circle(balls[range[0]].x, balls[range[0]].y, 0.0);
+    for &i in range.iter() {
+        centers = centers.union(&Aabb::from_circle(balls[i].x, balls[i].y, 0.0));
+    }
+
+    //(コスト, y軸か, 左に入れるビンの数)
+    let mut best: Option<(f64, bool, usize)> = None;
+    for &y_axis in &[false, true] {
+        let (min, max) = if y_axis {
+            (centers.y_min, centers.y_max)
+        } else {
+            (centers.x_min, centers.x_max)
+        };
+        if max - min <= f64::EPSILON {
+            continue;
+        }
+
+        let mut bins: [Option<Aabb>; SAH_BINS] = [None; SAH_BINS];
+        let mut counts = [0usize; SAH_BINS];
+        for &i in range.iter() {
+            let bin = sah_bin(&balls[i], y_axis, min, max);
+            let aabb = balls[i].aabb();
+            bins[bin] = Some(bins[bin].map_or(aabb, |bin_aabb| bin_aabb.union(&aabb)));
+            counts[bin] += 1;
+        }
+
+        //左から順に足していった大きさと数を覚えておいて、右から足しながら境目ごとのコストを出す
+        let mut left_costs = [0.0; SAH_BINS];
+        let mut left_aabb: Option<Aabb> = None;
+        let mut left_count = 0;
+        for bin in 0..SAH_BINS - 1 {
+            left_aabb = union_option(left_aabb, bins[bin]);
+            left_count += counts[bin];
+            left_costs[bin] = left_aabb.map_or(0.0, |aabb| aabb.size() * left_count as f64);
+        }
+        let mut right_aabb: Option<Aabb> = None;
+        let mut right_count = 0;
+        for bin in (1..SAH_BINS).rev() {
+            right_aabb = union_option(right_aabb, bins[bin]);
+            right_count += counts[bin];
+            //どちらかが空になる分け方は選ばない
+            if right_count == 0 || right_count == range.len() {
+                continue;
+            }
+            let cost = left_costs[bin - 1]
+                + right_aabb.map_or(0.0, |aabb| aabb.size() * right_count as f64);
+            if best.iter().all(|&(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, y_axis, bin));
+            }
+        }
+    }
+
+    //中心がすべて同じ点に重なっているときは分けようがないので半分ずつ
+    let (y_axis, split_bin) = match best {
+        Some((_, y_axis, split_bin)) => (y_axis, split_bin),
+        None => return median_split(balls, range, false),
+    };
+    let (min, max) = if y_axis {
+        (centers.y_min, centers.y_max)
+    } else {
+        (centers.x_min, centers.x_max)
+    };
+
+    //split_binより前のビンのボールを前に集める
+    let mut left = 0;
+    for i in 0..range.len() {
+        if sah_bin(&balls[range[i]], y_axis, min, max) < split_bin {
+            range.swap(left, i);
+            left += 1;
+        }
+    }
+    left
+}
+
+fn sah_bin(ball: &Ball, y_axis: bool, min: f64, max: f64) -> usize {
+    let value = if y_axis { ball.y } else { ball.x };
+    let bin = ((value - min) / (max - min) * SAH_BINS as f64) as usize;
+    bin.min(SAH_BINS - 1)
+}
+
+fn union_option(a: Option<Aabb>, b: Option<Aabb>) -> Option<Aabb> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(&b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+//毎回ツリーを作り直して、接触の可能性があるボールのペアを集める 各ペアは1回ずつ(idの小さい方が先)
+//WorldではBroadPhaseトレイトを通して使う bvhは前回のものを渡すとVecを使い回す
+pub fn broad_phase<'a>(balls: &'a [Ball], bvh: &mut Bvh) -> Vec<(&'a Ball, &'a Ball)> {
+    bvh.build(balls);
+
+    let mut balls_with_possible_contact: Vec<(&Ball, &Ball)> = vec![];
+    let mut overlap_tests = 0;
+    if let Some(root) = bvh.root() {
+        match bvh.traversal {
+            BvhTraversal::PerBall => {
+                for ball in balls.iter() {
+                    get_contact_with(
+                        ball,
+                        balls,
+                        bvh,
+                        root,
+                        &mut balls_with_possible_contact,
+                        &mut overlap_tests,
+                    );
+                }
+            }
+            BvhTraversal::DualTree => get_contact_with_top_down(
+                balls,
+                bvh,
+                root,
+                root,
+                &mut balls_with_possible_contact,
+                &mut overlap_tests,
+            ),
+        }
+    }
+    bvh.queries = balls.len();
+    bvh.overlap_tests = overlap_tests;
+
+    balls_with_possible_contact
+}
+
+//トップダウンでツリーどうしを重ねて走査する node==otherのときはその部分木の中どうしを調べる
+//(node, other)の組はどれも1回しか訪れないので、各ペアは1回ずつ見つかる 結果のVec以外はアロケーションしない
+fn get_contact_with_top_down<'a>(
+    balls: &'a [Ball],
+    bvh: &Bvh,
+    node: usize,
+    other: usize,
+    balls_with_possible_contact: &mut Vec<(&'a Ball, &'a Ball)>,
+    overlap_tests: &mut usize,
+) {
+    let (node_children, other_children) = (bvh.nodes[node].children, bvh.nodes[other].children);
+
+    if node == other {
+        match node_children {
+            //部分木の中どうしは、左右それぞれの中どうしと左右の間に分ける
+            Some((left, right)) => {
+                for &(a, b) in &[(left, left), (right, right), (left, right)] {
+                    get_contact_with_top_down(
+                        balls,
+                        bvh,
+                        a,
+                        b,
+                        balls_with_possible_contact,
+                        overlap_tests,
+                    );
+                }
+            }
+            //葉の中どうし
+            None => {
+                let indices = bvh.balls_in(node);
+                for (k, &i) in indices.iter().enumerate() {
+                    for &j in indices[k + 1..].iter() {
+                        push_if_intersects(
+                            &balls[i],
+                            &balls[j],
+                            balls_with_possible_contact,
+                            overlap_tests,
+                        );
+                    }
+                }
+            }
+        }
+        return;
+    }
+
+    //接触なし
+    let (node_aabb, other_aabb) = (bvh.nodes[node].aabb, bvh.nodes[other].aabb);
+    *overlap_tests += 1;
+    if !node_aabb.is_intersects(&other_aabb) {
+        return;
+    }
+
+    //葉どうしなら中のボールを1つずつ調べ、そうでなければ大きい方(葉でない方)を分けて降りる
+    let (split, keep, split_is_node) = match (node_children, other_children) {
+        (None, None) => {
+            for &i in bvh.balls_in(node) {
+                for &j in bvh.balls_in(other) {
+                    push_if_intersects(
+                        &balls[i],
+                        &balls[j],
+                        balls_with_possible_contact,
+                        overlap_tests,
+                    );
+                }
+            }
+            return;
+        }
+        (Some(children), None) => (children, other, true),
+        (None, Some(children)) => (children, node, false),
+        (Some(children), Some(other_children)) => {
+            if node_aabb.size() >= other_aabb.size() {
+                (children, other, true)
+            } else {
+                (other_children, node, false)
+            }
+        }
+    };
+    for &child in &[split.0, split.1] {
+        let (a, b) = if split_is_node {
+            (child, keep)
+        } else {
+            (keep, child)
+        };
+        get_contact_with_top_down(balls, bvh, a, b, balls_with_possible_contact, overlap_tests);
+    }
+}
+
+//2つのボールのAABBが重なっていれば、idの小さい方を先にしてペアに加える
+fn push_if_intersects<'a>(
+    a: &'a Ball,
+    b: &'a Ball,
+    balls_with_possible_contact: &mut Vec<(&'a Ball, &'a Ball)>,
+    overlap_tests: &mut usize,
+) {
+    *overlap_tests += 1;
+    if !a.aabb().is_intersects(&b.aabb()) {
+        return;
+    }
+    if a.id < b.id {
+        balls_with_possible_contact.push((a, b));
+    } else {
+        balls_with_possible_contact.push((b, a));
+    }
+}
+
+fn get_contact_with<'a>(
+    ball: &'a Ball,
+    balls: &'a [Ball],
+    bvh: &Bvh,
+    node: usize,
+    balls_with_possible_contact: &mut Vec<(&'a Ball, &'a Ball)>,
+    overlap_tests: &mut usize,
+) {
+    let ball_aabb = ball.aabb();
+
+    //接触なし
+    *overlap_tests += 1;
+    if !ball_aabb.is_intersects(&bvh.nodes[node].aabb) {
+        return;
+    }
+
+    match bvh.nodes[node].children {
+        //リーフノードでない場合、再帰的にツリーを降下する
+        Some((left, right)) => {
+            get_contact_with(
+                ball,
+                balls,
+                bvh,
+                left,
+                balls_with_possible_contact,
+                overlap_tests,
+            );
+            get_contact_with(
+                ball,
+                balls,
+                bvh,
+                right,
+                balls_with_possible_contact,
+                overlap_tests,
+            );
+        }
+        //葉には複数のボールが入っているので1つずつ調べる
+        None => {
+            for &i in bvh.balls_in(node) {
+                let other = &balls[i];
+                if other.id <= ball.id {
+                    continue;
+                }
+                *overlap_tests += 1;
+                if !ball_aabb.is_intersects(&other.aabb()) {
+                    continue;
+                }
+                balls_with_possible_contact.push((ball, other));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balls(positions: &[(f64, f64)]) -> Vec<Ball> {
+        positions
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| Ball::new(x, y, 0.0, 0.0, "rgb(0,0,0)", 5.0, i as i32))
+            .collect()
+    }
+
+    //子の範囲が親の範囲を2つに分けていて、AABBが子を含み、葉が小さいことを確かめる
+    fn validate(bvh: &Bvh, balls: &[Ball], node: usize) {
+        let current = &bvh.nodes[node];
+        for &i in bvh.balls_in(node) {
+            let aabb = balls[i].aabb();
+            assert!(current.aabb.x_min <= aabb.x_min && aabb.x_max <= current.aabb.x_max);
+            assert!(current.aabb.y_min <= aabb.y_min && aabb.y_max <= current.aabb.y_max);
+        }
+        match current.children {
+            Some((left, right)) => {
+                assert_eq!(bvh.nodes[left].start, current.start);
+                assert_eq!(bvh.nodes[left].end, bvh.nodes[right].start);
+                assert_eq!(bvh.nodes[right].end, current.end);
+                assert!(bvh.nodes[left].start < bvh.nodes[left].end);
+                assert!(bvh.nodes[right].start < bvh.nodes[right].end);
+                validate(bvh, balls, left);
+                validate(bvh, balls, right);
+            }
+            None => assert!(current.end - current.start <= LEAF_SIZE),
+        }
+    }
+
+    #[test]
+    fn indices_are_a_permutation() {
+        let positions: Vec<(f64, f64)> = (0..100)
+            .map(|i| ((i * 37 % 400) as f64, (i * 91 % 300) as f64))
+            .collect();
+        let balls = balls(&positions);
+        let mut bvh = Bvh::default();
+        bvh.build(&balls);
+
+        let mut sorted = bvh.indices.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..100).collect::<Vec<usize>>());
+        validate(&bvh, &balls, bvh.root().unwrap());
+    }
+
+    //ツリーをたどって集めたペアは、全部の組み合わせを調べたものと同じになる
+    #[test]
+    fn broad_phase_matches_brute_force() {
+        let positions: Vec<(f64, f64)> = (0..300)
+            .map(|i| ((i * 37 % 193) as f64, (i * 91 % 151) as f64))
+            .collect();
+        let balls = balls(&positions);
+
+        let mut pairs: Vec<(i32, i32)> = broad_phase(&balls, &mut Bvh::default())
+            .iter()
+            .map(|(a, b)| (a.id, b.id))
+            .collect();
+        pairs.sort();
+
+        let mut expected = vec![];
+        for a in balls.iter() {
+            for b in balls.iter().skip(a.id as usize + 1) {
+                if a.aabb().is_intersects(&b.aabb()) {
+                    expected.push((a.id, b.id));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn sah_tree_is_valid_and_matches_brute_force() {
+        let positions: Vec<(f64, f64)> = (0..300)
+            .map(|i| ((i * 37 % 193) as f64, (i * 91 % 151) as f64))
+            .collect();
+        let balls = balls(&positions);
+        let mut midpoint = Bvh::default();
+        let mut sah = Bvh::with_builder(BvhBuilder::Sah);
+
+        let ids = |pairs: Vec<(&Ball, &Ball)>| {
+            let mut ids: Vec<(i32, i32)> = pairs.iter().map(|(a, b)| (a.id, b.id)).collect();
+            ids.sort();
+            ids
+        };
+        let expected = ids(broad_phase(&balls, &mut midpoint));
+        assert_eq!(ids(broad_phase(&balls, &mut sah)), expected);
+        validate(&sah, &balls, 0);
+        assert_eq!(sah.queries, 300);
+    }
+
+    //固まりがいくつかある配置では、中点で分けるより問い合わせで調べるAABBが少なくて済む
+    #[test]
+    fn sah_needs_fewer_overlap_tests_on_clusters() {
+        let mut positions = vec![];
+        for &(cx, cy) in &[(50.0, 50.0), (900.0, 80.0), (120.0, 650.0), (700.0, 600.0)] {
+            for i in 0..60 {
+                positions.push((cx + (i % 8) as f64 * 9.0, cy + (i / 8) as f64 * 9.0));
+            }
+        }
+        let balls = balls(&positions);
+
+        let mut midpoint = Bvh::default();
+        broad_phase(&balls, &mut midpoint);
+        let mut sah = Bvh::with_builder(BvhBuilder::Sah);
+        broad_phase(&balls, &mut sah);
+
+        assert!(
+            sah.average_overlap_tests() < midpoint.average_overlap_tests(),
+            "sah {} midpoint {}",
+            sah.average_overlap_tests(),
+            midpoint.average_overlap_tests()
+        );
+    }
+
+    //木どうしでたどっても、ボールごとに問い合わせたときと同じペアが1回ずつ見つかる
+    #[test]
+    fn dual_tree_traversal_matches_per_ball_queries() {
+        let positions: Vec<(f64, f64)> = (0..300)
+            .map(|i| ((i * 37 % 193) as f64, (i * 91 % 151) as f64))
+            .chain((0..10).map(|_| (50.0, 50.0)))
+            .collect();
+        let balls = balls(&positions);
+        let ids = |pairs: Vec<(&Ball, &Ball)>| {
+            let mut ids: Vec<(i32, i32)> = pairs.iter().map(|(a, b)| (a.id, b.id)).collect();
+            ids.sort();
+            ids
+        };
+
+        for &builder in &[BvhBuilder::Midpoint, BvhBuilder::Sah] {
+            let mut per_ball = Bvh::with_builder(builder);
+            let expected = ids(broad_phase(&balls, &mut per_ball));
+            let mut dual_tree = Bvh {
+                traversal: BvhTraversal::DualTree,
+                ..Bvh::with_builder(builder)
+            };
+            let pairs = ids(broad_phase(&balls, &mut dual_tree));
+
+            assert!(pairs.iter().all(|(a, b)| a < b));
+            let mut deduped = pairs.clone();
+            deduped.dedup();
+            assert_eq!(deduped.len(), pairs.len());
+            assert_eq!(pairs, expected);
+            //同じ組を2回調べないので、ボールごとに問い合わせるより調べるAABBが少ない
+            assert!(
+                dual_tree.average_overlap_tests() < per_ball.average_overlap_tests(),
+                "{:?} dual {} per ball {}",
+                builder,
+                dual_tree.average_overlap_tests(),
+                per_ball.average_overlap_tests()
+            );
+        }
+    }
+
+    #[test]
+    fn from_name_selects_traversal() {
+        let bvh = Bvh::from_name("sah-dual").unwrap();
+        assert_eq!(
+            (bvh.builder, bvh.traversal),
+            (BvhBuilder::Sah, BvhTraversal::DualTree)
+        );
+        let bvh = Bvh::from_name("midpoint").unwrap();
+        assert_eq!(
+            (bvh.builder, bvh.traversal),
+            (BvhBuilder::Midpoint, BvhTraversal::PerBall)
+        );
+        assert!(Bvh::from_name("dual").is_none());
+    }
+
+    //同じ位置に重なったボールばかりでも無限に分け続けない
+    #[test]
+    fn identical_positions() {
+        let balls = balls(&[(10.0, 10.0); 20]);
+        for &builder in &[BvhBuilder::Midpoint, BvhBuilder::Sah] {
+            let mut bvh = Bvh::with_builder(builder);
+            bvh.build(&balls);
+            validate(&bvh, &balls, 0);
+        }
+    }
+
+    //作り直してもVecを使い回す
+    #[test]
+    fn rebuild_reuses_storage() {
+        let positions: Vec<(f64, f64)> = (0..50).map(|i| (i as f64 * 12.0, 0.0)).collect();
+        let mut balls = balls(&positions);
+        let mut bvh = Bvh::default();
+        bvh.build(&balls);
+        let capacity = (bvh.nodes.capacity(), bvh.indices.capacity());
+
+        for ball in balls.iter_mut() {
+            ball.y += 3.0;
+        }
+        bvh.build(&balls);
+        assert_eq!((bvh.nodes.capacity(), bvh.indices.capacity()), capacity);
+        validate(&bvh, &balls, 0);
+
+        bvh.build(&[]);
+        assert!(bvh.root().is_none());
+    }
+}
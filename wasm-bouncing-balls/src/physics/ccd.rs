@@ -0,0 +1,231 @@
+use super::narrow_phase::Contact;
+use super::response::{resolve_contacts, CollisionSettings};
+use super::Ball;
+
+//連続的当たり判定(CCD)
+//ステップの終わりの位置だけを見ると、速くて小さいボールは1ステップで壁や他のボールを飛び越えてしまう(トンネリング)
+//ボールがステップの間まっすぐ動くとして、円が最初に触れる時刻(TOI)を求め、その時刻まで進めて跳ね返す
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wall {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+//ステップの途中で起きる衝突 ボールはidで持つ
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Impact {
+    Ball(i32, i32),
+    Wall(i32, Wall),
+}
+
+//2つの円が今の速度のまま動いたとき、max_time秒以内に最初に触れる時刻
+//すでに重なっている(接している)ペアや離れていくペアはNone 重なりは狭域当たり判定で押し戻す
+pub fn ball_ball_toi(a: &Ball, b: &Ball, max_time: f64) -> Option<f64> {
+    //aを止めて、bがaに対して相対速度で動くと考える
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    let dvx = b.vel_x - a.vel_x;
+    let dvy = b.vel_y - a.vel_y;
+    let radius_sum = a.size + b.size;
+
+    //|d + dv*t| = radius_sum をtについて解く
+    let qa = dvx * dvx + dvy * dvy;
+    let qb = 2.0 * (dx * dvx + dy * dvy);
+    let qc = dx * dx + dy * dy - radius_sum * radius_sum;
+
+    if qc <= 0.0 || qb >= 0.0 || qa <= f64::EPSILON {
+        return None;
+    }
+    let discriminant = qb * qb - 4.0 * qa * qc;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    //近づいてくるので小さい方の解が最初に触れる時刻 qc>0,qb<0なので必ず0以上
+    let time = (-qb - discriminant.sqrt()) / (2.0 * qa);
+    if time > max_time {
+        return None;
+    }
+    Some(time)
+}
+
+//ボールが今の速度のまま動いたとき、max_time秒以内に最初に当たる壁とその時刻
+pub fn ball_wall_toi(ball: &Ball, width: f64, height: f64, max_time: f64) -> Option<(f64, Wall)> {
+    let mut earliest: Option<(f64, Wall)> = None;
+    let mut check = |distance: f64, speed: f64, wall: Wall| {
+        //壁に向かって動いていて、まだ壁の内側にいるときだけ
+        if speed <= 0.0 || distance <= 0.0 {
+            return;
+        }
+        let time = distance / speed;
+        if time <= max_time && earliest.iter().all(|&(t, _)| time < t) {
+            earliest = Some((time, wall));
+        }
+    };
+
+    check(ball.x - ball.size, -ball.vel_x, Wall::Left);
+    check(width - ball.size - ball.x, ball.vel_x, Wall::Right);
+    check(ball.y - ball.size, -ball.vel_y, Wall::Top);
+    check(height - ball.size - ball.y, ball.vel_y, Wall::Bottom);
+
+    earliest
+}
+
+//広域当たり判定のペアと壁の中から、max_time秒以内に最初に起きる衝突を探す
+pub fn earliest_impact(
+    balls: &[Ball],
+    pairs: &[(i32, i32)],
+    width: f64,
+    height: f64,
+    max_time: f64,
+) -> Option<(f64, Impact)> {
+    let mut earliest: Option<(f64, Impact)> = None;
+    let mut consider = |time: f64, impact: Impact| {
+        if earliest.iter().all(|&(t, _)| time < t) {
+            earliest = Some((time, impact));
+        }
+    };
+
+    for &(a, b) in pairs.iter() {
+        if let Some(time) = ball_ball_toi(&balls[a as usize], &balls[b as usize], max_time) {
+            consider(time, Impact::Ball(a, b));
+        }
+    }
+    for ball in balls.iter() {
+        if let Some((time, wall)) = ball_wall_toi(ball, width, height, max_time) {
+            consider(time, Impact::Wall(ball.id, wall));
+        }
+    }
+
+    earliest
+}
+
+//衝突時刻まで進めたあとに呼ぶ 触れた瞬間なのでめり込みは0として速度だけ変える
+pub fn resolve_impact(balls: &mut [Ball], impact: Impact, settings: &CollisionSettings) {
+    match impact {
+        Impact::Ball(a, b) => {
+            let (ball_a, ball_b) = (&balls[a as usize], &balls[b as usize]);
+            let dx = ball_b.x - ball_a.x;
+            let dy = ball_b.y - ball_a.y;
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance <= f64::EPSILON {
+                return;
+            }
+            let (normal_x, normal_y) = (dx / distance, dy / distance);
+            let contact = Contact {
+                a,
+                b,
+                normal_x,
+                normal_y,
+                depth: 0.0,
+                point_x: ball_a.x + normal_x * ball_a.size,
+                point_y: ball_a.y + normal_y * ball_a.size,
+            };
+            resolve_contacts(balls, &[contact], settings);
+        }
+        //壁の跳ね返りはkeep_insideと同じく速度を反転させるだけ
+        Impact::Wall(id, wall) => {
+            let ball = &mut balls[id as usize];
+            match wall {
+                Wall::Left => ball.vel_x = ball.vel_x.abs(),
+                Wall::Right => ball.vel_x = -ball.vel_x.abs(),
+                Wall::Top => ball.vel_y = ball.vel_y.abs(),
+                Wall::Bottom => ball.vel_y = -ball.vel_y.abs(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ball(x: f64, y: f64, vel_x: f64, vel_y: f64, size: f64, id: i32) -> Ball {
+        Ball::new(x, y, vel_x, vel_y, "rgb(0,0,0)", size, id)
+    }
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn head_on_time_of_impact() {
+        //間が20px空いていて、相対速度40px/sで近づく
+        let a = ball(0.0, 0.0, 30.0, 0.0, 5.0, 0);
+        let b = ball(30.0, 0.0, -10.0, 0.0, 5.0, 1);
+        assert!(approx(ball_ball_toi(&a, &b, 1.0).unwrap(), 0.5));
+        assert!(approx(ball_ball_toi(&b, &a, 1.0).unwrap(), 0.5));
+        assert!(ball_ball_toi(&a, &b, 0.4).is_none());
+    }
+
+    //ステップの始めと終わりでは重なっていないが、途中ですれ違うペア
+    #[test]
+    fn fast_balls_passing_through_each_other() {
+        let a = ball(0.0, 0.0, 6000.0, 0.0, 2.0, 0);
+        let b = ball(50.0, 1.0, -6000.0, 0.0, 2.0, 1);
+        let time = ball_ball_toi(&a, &b, 1.0 / 60.0).unwrap();
+
+        let distance = ((50.0 - 12000.0 * time).powi(2) + 1.0).sqrt();
+        assert!(approx(distance, 4.0));
+    }
+
+    #[test]
+    fn missing_and_separating_balls_have_no_impact() {
+        let a = ball(0.0, 0.0, 10.0, 0.0, 5.0, 0);
+        let above = ball(30.0, 20.0, -10.0, 0.0, 5.0, 1);
+        assert!(ball_ball_toi(&a, &above, 10.0).is_none());
+
+        let behind = ball(-30.0, 0.0, -10.0, 0.0, 5.0, 1);
+        assert!(ball_ball_toi(&a, &behind, 10.0).is_none());
+
+        //重なっているペアはCCDでは扱わない
+        let overlapping = ball(8.0, 0.0, -10.0, 0.0, 5.0, 1);
+        assert!(ball_ball_toi(&a, &overlapping, 10.0).is_none());
+    }
+
+    #[test]
+    fn wall_time_of_impact() {
+        let moving_right = ball(50.0, 50.0, 100.0, 0.0, 10.0, 0);
+        assert_eq!(
+            ball_wall_toi(&moving_right, 100.0, 100.0, 1.0),
+            Some((0.4, Wall::Right))
+        );
+        assert!(ball_wall_toi(&moving_right, 100.0, 100.0, 0.3).is_none());
+
+        //先に当たる方の壁を返す
+        let diagonal = ball(20.0, 50.0, -10.0, 100.0, 10.0, 0);
+        let (time, wall) = ball_wall_toi(&diagonal, 100.0, 100.0, 10.0).unwrap();
+        assert_eq!(wall, Wall::Bottom);
+        assert!(approx(time, 0.4));
+    }
+
+    #[test]
+    fn earliest_impact_picks_first_event() {
+        let balls = vec![
+            ball(50.0, 50.0, 100.0, 0.0, 5.0, 0),
+            ball(80.0, 50.0, 0.0, 0.0, 5.0, 1),
+        ];
+        let (time, impact) = earliest_impact(&balls, &[(0, 1)], 1000.0, 1000.0, 1.0).unwrap();
+        assert_eq!(impact, Impact::Ball(0, 1));
+        assert!(approx(time, 0.2));
+
+        //ペアに入っていなければボール同士の衝突は見ないので、壁の方が先になる
+        let (_, impact) = earliest_impact(&balls, &[], 1000.0, 1000.0, 100.0).unwrap();
+        assert_eq!(impact, Impact::Wall(0, Wall::Right));
+    }
+
+    #[test]
+    fn resolve_wall_impact_reflects_velocity() {
+        let mut balls = vec![ball(90.0, 50.0, 100.0, -20.0, 10.0, 0)];
+        resolve_impact(
+            &mut balls,
+            Impact::Wall(0, Wall::Right),
+            &CollisionSettings::default(),
+        );
+        assert!(approx(balls[0].vel_x, -100.0));
+        assert!(approx(balls[0].vel_y, -20.0));
+    }
+}
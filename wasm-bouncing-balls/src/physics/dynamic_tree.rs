@@ -0,0 +1,498 @@
+use super::Aabb;
+
+//毎ステップ作り直さずに持ち続けるAABBツリー(Box2Dのb2DynamicTreeと同じ作り)
+//葉にはボールのAABBを少し太らせたもの(fat AABB)を入れておき、ボールがその中に収まっている間は木をいじらない
+//はみ出したときだけ葉を抜いて入れ直し、親をたどってAABBと高さを直しながら回転で釣り合いを取る
+//ノードはVecに並べて番号で指し合う 空いたノードは使い回す
+
+//fat AABBを実際のAABBより太らせる量(px)
+const AABB_MARGIN: f64 = 4.0;
+//動いている向きには移動量のこの倍だけ先回りして太らせる
+const DISPLACEMENT_MULTIPLIER: f64 = 2.0;
+
+impl Aabb {
+    fn contains(&self, other: &Aabb) -> bool {
+        self.x_min <= other.x_min
+            && self.y_min <= other.y_min
+            && other.x_max <= self.x_max
+            && other.y_max <= self.y_max
+    }
+
+    fn fattened(&self, margin: f64) -> Aabb {
+        Aabb {
+            x_max: self.x_max + margin,
+            x_min: self.x_min - margin,
+            y_max: self.y_max + margin,
+            y_min: self.y_min - margin,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TreeNode {
+    aabb: Aabb,
+    //空きノードのときは次の空きノードを指す
+    parent: Option<usize>,
+    child1: Option<usize>,
+    child2: Option<usize>,
+    //葉は0 空きノードは-1
+    height: i32,
+    //葉に入っているボールのid
+    ball: i32,
+}
+
+impl TreeNode {
+    fn is_leaf(&self) -> bool {
+        self.child1.is_none()
+    }
+}
+
+#[derive(Default)]
+pub struct DynamicTree {
+    nodes: Vec<TreeNode>,
+    root: Option<usize>,
+    free_list: Option<usize>,
+}
+
+impl DynamicTree {
+    pub fn new() -> DynamicTree {
+        DynamicTree::default()
+    }
+
+    //ボールのAABBを太らせて葉として入れる 戻り値の番号(プロキシ)で後から動かしたり消したりする
+    pub fn create_proxy(&mut self, aabb: &Aabb, ball: i32) -> usize {
+        let proxy = self.allocate_node();
+        self.nodes[proxy].aabb = aabb.fattened(AABB_MARGIN);
+        self.nodes[proxy].ball = ball;
+        self.nodes[proxy].height = 0;
+        self.insert_leaf(proxy);
+        proxy
+    }
+
+    pub fn destroy_proxy(&mut self, proxy: usize) {
+        debug_assert!(self.nodes[proxy].is_leaf());
+        self.remove_leaf(proxy);
+        self.free_node(proxy);
+    }
+
+    //ボールが動いたあとのAABBと移動量を渡す fat AABBからはみ出していたときだけ入れ直してtrueを返す
+    pub fn move_proxy(&mut self, proxy: usize, aabb: &Aabb, motion_x: f64, motion_y: f64) -> bool {
+        let tree_aabb = self.nodes[proxy].aabb;
+        let mut fat_aabb = aabb.fattened(AABB_MARGIN);
+        let (dx, dy) = (
+            motion_x * DISPLACEMENT_MULTIPLIER,
+            motion_y * DISPLACEMENT_MULTIPLIER,
+        );
+        fat_aabb.x_max += dx.max(0.0);
+        fat_aabb.x_min += dx.min(0.0);
+        fat_aabb.y_max += dy.max(0.0);
+        fat_aabb.y_min += dy.min(0.0);
+
+        if tree_aabb.contains(aabb) {
+            //速く動いていたころの大きすぎるfat AABBが残っていると無駄なペアが増えるので、そのときだけは入れ直す
+            let huge_aabb = fat_aabb.fattened(4.0 * AABB_MARGIN);
+            if huge_aabb.contains(&tree_aabb) {
+                return false;
+            }
+        }
+
+        self.remove_leaf(proxy);
+        self.nodes[proxy].aabb = fat_aabb;
+        self.insert_leaf(proxy);
+        true
+    }
+
+    //木の高さ 葉だけなら0、空なら-1
+    #[cfg(test)]
+    pub fn height(&self) -> i32 {
+        self.root.map_or(-1, |root| self.nodes[root].height)
+    }
+
+    //fat AABBが重なる葉のペアをすべて集める 各ペアは1回ずつ(idの小さい方が先)
+    //葉ごとに根から問い合わせるより、木どうしを重ねて同時に降りていく方が調べるノードが少なくて済む
+    pub fn pairs(&self) -> Vec<(i32, i32)> {
+        let mut pairs = vec![];
+        let root = match self.root {
+            Some(root) => root,
+            None => return pairs,
+        };
+
+        //(a,b)の組を調べる a==bのときはその部分木の中どうし
+        let mut stack: Vec<(usize, usize)> = vec![(root, root)];
+        while let Some((a, b)) = stack.pop() {
+            let (node_a, node_b) = (&self.nodes[a], &self.nodes[b]);
+            if a == b {
+                if !node_a.is_leaf() {
+                    let child1 = node_a.child1.unwrap();
+                    let child2 = node_a.child2.unwrap();
+                    stack.push((child1, child1));
+                    stack.push((child2, child2));
+                    stack.push((child1, child2));
+                }
+                continue;
+            }
+            if !node_a.aabb.is_intersects(&node_b.aabb) {
+                continue;
+            }
+            match (node_a.is_leaf(), node_b.is_leaf()) {
+                (true, true) => {
+                    let (ball_a, ball_b) = (node_a.ball, node_b.ball);
+                    pairs.push((ball_a.min(ball_b), ball_a.max(ball_b)));
+                }
+                //大きい方(葉でない方)を分ける
+                (false, true) => Self::push_children(&mut stack, node_a, b),
+                (true, false) => Self::push_children(&mut stack, node_b, a),
+                (false, false) => {
+                    if node_a.aabb.size() >= node_b.aabb.size() {
+                        Self::push_children(&mut stack, node_a, b);
+                    } else {
+                        Self::push_children(&mut stack, node_b, a);
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    fn push_children(stack: &mut Vec<(usize, usize)>, parent: &TreeNode, other: usize) {
+        stack.push((parent.child1.unwrap(), other));
+        stack.push((parent.child2.unwrap(), other));
+    }
+
+    //デバッグ表示用に、使われているノードのAABBを葉かどうかと一緒に渡す
+    pub fn for_each_aabb<F: FnMut(&Aabb, bool)>(&self, mut callback: F) {
+        for node in self.nodes.iter() {
+            if node.height >= 0 {
+                callback(&node.aabb, node.is_leaf());
+            }
+        }
+    }
+
+    fn allocate_node(&mut self) -> usize {
+        let node = TreeNode {
+            aabb: Aabb::from_circle(0.0, 0.0, 0.0),
+            parent: None,
+            child1: None,
+            child2: None,
+            height: 0,
+            ball: -1,
+        };
+        match self.free_list {
+            Some(index) => {
+                self.free_list = self.nodes[index].parent;
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn free_node(&mut self, index: usize) {
+        self.nodes[index].parent = self.free_list;
+        self.nodes[index].child1 = None;
+        self.nodes[index].child2 = None;
+        self.nodes[index].height = -1;
+        self.free_list = Some(index);
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let root = match self.root {
+            Some(root) => root,
+            None => {
+                self.root = Some(leaf);
+                self.nodes[leaf].parent = None;
+                return;
+            }
+        };
+
+        //兄弟にする葉を探す 入れたときに増えるAABBの周長(の半分)が一番小さくなる方へ降りていく
+        let leaf_aabb = self.nodes[leaf].aabb;
+        let mut index = root;
+        while !self.nodes[index].is_leaf() {
+            let child1 = self.nodes[index].child1.unwrap();
+            let child2 = self.nodes[index].child2.unwrap();
+
+            let size = self.nodes[index].aabb.size();
+            let combined_size = self.nodes[index].aabb.union(&leaf_aabb).size();
+
+            //ここで新しい親を作って葉と兄弟にするときのコスト
+            let cost = 2.0 * combined_size;
+            //これより下に入れると、このノードのAABBが広がる分は必ずかかる
+            let inheritance_cost = 2.0 * (combined_size - size);
+
+            let child_cost = |child: usize| {
+                let node = &self.nodes[child];
+                let combined = node.aabb.union(&leaf_aabb).size();
+                if node.is_leaf() {
+                    combined + inheritance_cost
+                } else {
+                    combined - node.aabb.size() + inheritance_cost
+                }
+            };
+            let cost1 = child_cost(child1);
+            let cost2 = child_cost(child2);
+
+            if cost < cost1 && cost < cost2 {
+                break;
+            }
+            index = if cost1 < cost2 { child1 } else { child2 };
+        }
+        let sibling = index;
+
+        //兄弟と葉をまとめる親を作って、兄弟がいた場所に差し込む
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate_node();
+        self.nodes[new_parent].parent = old_parent;
+        self.nodes[new_parent].aabb = leaf_aabb.union(&self.nodes[sibling].aabb);
+        self.nodes[new_parent].height = self.nodes[sibling].height + 1;
+        self.nodes[new_parent].child1 = Some(sibling);
+        self.nodes[new_parent].child2 = Some(leaf);
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        self.replace_child(old_parent, sibling, new_parent);
+
+        self.refit(self.nodes[leaf].parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+
+        //葉の親を消して、兄弟を親がいた場所に上げる
+        let parent = self.nodes[leaf].parent.unwrap();
+        let grand_parent = self.nodes[parent].parent;
+        let sibling = if self.nodes[parent].child1 == Some(leaf) {
+            self.nodes[parent].child2.unwrap()
+        } else {
+            self.nodes[parent].child1.unwrap()
+        };
+
+        self.replace_child(grand_parent, parent, sibling);
+        self.nodes[sibling].parent = grand_parent;
+        self.free_node(parent);
+
+        self.refit(grand_parent);
+    }
+
+    //parentの子のold_childをnew_childに付け替える parentがNoneならnew_childを根にする
+    fn replace_child(&mut self, parent: Option<usize>, old_child: usize, new_child: usize) {
+        match parent {
+            Some(parent) => {
+                if self.nodes[parent].child1 == Some(old_child) {
+                    self.nodes[parent].child1 = Some(new_child);
+                } else {
+                    self.nodes[parent].child2 = Some(new_child);
+                }
+            }
+            None => self.root = Some(new_child),
+        }
+    }
+
+    //indexから根までたどって、釣り合いを取りながら高さとAABBを子から計算し直す
+    fn refit(&mut self, mut index: Option<usize>) {
+        while let Some(current) = index {
+            let current = self.balance(current);
+
+            let child1 = self.nodes[current].child1.unwrap();
+            let child2 = self.nodes[current].child2.unwrap();
+            self.nodes[current].height =
+                1 + self.nodes[child1].height.max(self.nodes[child2].height);
+            self.nodes[current].aabb = self.nodes[child1].aabb.union(&self.nodes[child2].aabb);
+
+            index = self.nodes[current].parent;
+        }
+    }
+
+    //aの左右の高さが2以上違えば、高い方の子を1段持ち上げる(回転) 回転後にaの場所にいるノードを返す
+    //
+    //       a              c
+    //      / \            / \
+    //     b   c   -->    a   f   (fの方が高いとき。低い方のgをaの子にする)
+    //        / \        / \
+    //       f   g      b   g
+    fn balance(&mut self, a: usize) -> usize {
+        if self.nodes[a].is_leaf() || self.nodes[a].height < 2 {
+            return a;
+        }
+        let b = self.nodes[a].child1.unwrap();
+        let c = self.nodes[a].child2.unwrap();
+        let balance = self.nodes[c].height - self.nodes[b].height;
+
+        if balance > 1 {
+            self.rotate_up(a, c, b, false);
+            return c;
+        }
+        if balance < -1 {
+            self.rotate_up(a, b, c, true);
+            return b;
+        }
+        a
+    }
+
+    //aの子upをaの場所に持ち上げる stayはaに残るもう一方の子
+    //upの子のうち高い方をupに残し、低い方をaに渡す upがaの左の子(child1)だったときはup_is_child1
+    fn rotate_up(&mut self, a: usize, up: usize, stay: usize, up_is_child1: bool) {
+        let f = self.nodes[up].child1.unwrap();
+        let g = self.nodes[up].child2.unwrap();
+
+        //aとupを入れ替える
+        let a_parent = self.nodes[a].parent;
+        self.nodes[up].child1 = Some(a);
+        self.nodes[up].parent = a_parent;
+        self.nodes[a].parent = Some(up);
+        self.replace_child(a_parent, a, up);
+
+        let (keep, give) = if self.nodes[f].height > self.nodes[g].height {
+            (f, g)
+        } else {
+            (g, f)
+        };
+        self.nodes[up].child2 = Some(keep);
+        if up_is_child1 {
+            self.nodes[a].child1 = Some(give);
+        } else {
+            self.nodes[a].child2 = Some(give);
+        }
+        self.nodes[give].parent = Some(a);
+
+        self.nodes[a].aabb = self.nodes[stay].aabb.union(&self.nodes[give].aabb);
+        self.nodes[up].aabb = self.nodes[a].aabb.union(&self.nodes[keep].aabb);
+        self.nodes[a].height = 1 + self.nodes[stay].height.max(self.nodes[give].height);
+        self.nodes[up].height = 1 + self.nodes[a].height.max(self.nodes[keep].height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(x: f64, y: f64) -> Aabb {
+        Aabb::from_circle(x, y, 5.0)
+    }
+
+    //親子の指し合い、高さ、AABBが子を含むこと、左右の高さの差が1以内であることを確かめて、葉の数を返す
+    fn validate(tree: &DynamicTree) -> usize {
+        fn visit(tree: &DynamicTree, index: usize, parent: Option<usize>) -> usize {
+            let node = &tree.nodes[index];
+            assert_eq!(node.parent, parent);
+            if node.is_leaf() {
+                assert_eq!(node.height, 0);
+                return 1;
+            }
+            let child1 = node.child1.unwrap();
+            let child2 = node.child2.unwrap();
+            let (height1, height2) = (tree.nodes[child1].height, tree.nodes[child2].height);
+            assert_eq!(node.height, 1 + height1.max(height2));
+            assert!((height1 - height2).abs() <= 1);
+            assert!(node.aabb.contains(&tree.nodes[child1].aabb));
+            assert!(node.aabb.contains(&tree.nodes[child2].aabb));
+            visit(tree, child1, Some(index)) + visit(tree, child2, Some(index))
+        }
+        tree.root.map_or(0, |root| visit(tree, root, None))
+    }
+
+    fn brute_force_pairs(tree: &DynamicTree, proxies: &[usize]) -> Vec<(i32, i32)> {
+        let mut pairs = vec![];
+        for (i, &a) in proxies.iter().enumerate() {
+            for (j, &b) in proxies.iter().enumerate().skip(i + 1) {
+                if tree.nodes[a].aabb.is_intersects(&tree.nodes[b].aabb) {
+                    pairs.push((i as i32, j as i32));
+                }
+            }
+        }
+        pairs
+    }
+
+    //一列に並べて入れる(回転がないと片側に伸びきる)並びでも高さがlog程度に収まる
+    #[test]
+    fn sequential_inserts_stay_balanced() {
+        let mut tree = DynamicTree::new();
+        for i in 0..1024 {
+            tree.create_proxy(&circle(i as f64 * 20.0, 0.0), i);
+        }
+        assert_eq!(validate(&tree), 1024);
+        assert!(tree.height() <= 20, "height {}", tree.height());
+    }
+
+    #[test]
+    fn remove_and_reuse_nodes() {
+        let mut tree = DynamicTree::new();
+        let proxies: Vec<usize> = (0..100)
+            .map(|i| tree.create_proxy(&circle((i % 10) as f64 * 30.0, (i / 10) as f64 * 30.0), i))
+            .collect();
+        let allocated = tree.nodes.len();
+
+        for &proxy in proxies.iter().step_by(2) {
+            tree.destroy_proxy(proxy);
+        }
+        assert_eq!(validate(&tree), 50);
+
+        for i in 0..50 {
+            tree.create_proxy(&circle(i as f64, 500.0), 100 + i);
+        }
+        assert_eq!(validate(&tree), 100);
+        //空いたノードを使い回すので増えない
+        assert_eq!(tree.nodes.len(), allocated);
+
+        for &proxy in proxies.iter().skip(1).step_by(2) {
+            tree.destroy_proxy(proxy);
+        }
+        assert_eq!(validate(&tree), 50);
+    }
+
+    //fat AABBの中で少し動いただけなら入れ直さない
+    #[test]
+    fn small_moves_do_not_reinsert() {
+        let mut tree = DynamicTree::new();
+        let proxy = tree.create_proxy(&circle(100.0, 100.0), 0);
+        tree.create_proxy(&circle(200.0, 100.0), 1);
+
+        assert!(!tree.move_proxy(proxy, &circle(101.0, 100.0), 1.0, 0.0));
+        assert!(tree.move_proxy(proxy, &circle(150.0, 100.0), 49.0, 0.0));
+        assert!(tree.nodes[proxy].aabb.contains(&circle(150.0, 100.0)));
+        //移動した向きに先回りして太っている
+        assert!(tree.nodes[proxy].aabb.x_max > 155.0 + AABB_MARGIN);
+        assert_eq!(validate(&tree), 2);
+    }
+
+    #[test]
+    fn pairs_match_brute_force_after_moves() {
+        let mut tree = DynamicTree::new();
+        let mut positions: Vec<(f64, f64)> = (0..200)
+            .map(|i| ((i * 37 % 400) as f64, (i * 91 % 300) as f64))
+            .collect();
+        let proxies: Vec<usize> = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| tree.create_proxy(&circle(x, y), i as i32))
+            .collect();
+
+        for frame in 0..30 {
+            for (i, position) in positions.iter_mut().enumerate() {
+                let motion_x = ((i + frame) % 7) as f64 - 3.0;
+                let motion_y = ((i * 3 + frame) % 5) as f64 - 2.0;
+                position.0 += motion_x;
+                position.1 += motion_y;
+                tree.move_proxy(
+                    proxies[i],
+                    &circle(position.0, position.1),
+                    motion_x,
+                    motion_y,
+                );
+            }
+            assert_eq!(validate(&tree), 200);
+            for (i, &(x, y)) in positions.iter().enumerate() {
+                assert!(tree.nodes[proxies[i]].aabb.contains(&circle(x, y)));
+            }
+
+            let mut pairs = tree.pairs();
+            pairs.sort();
+            assert_eq!(pairs, brute_force_pairs(&tree, &proxies));
+        }
+    }
+}
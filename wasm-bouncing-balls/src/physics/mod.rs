@@ -0,0 +1,14 @@
+//ボールの動きと当たり判定 web-sysには依存しないので、ブラウザなしで動かしてcargo testで確かめられる
+//描画はrender、画面の入力や毎フレームの呼び出しはlib.rsが受け持つ
+mod aabb;
+mod ball;
+pub mod broad_phase;
+pub mod bvh;
+pub mod ccd;
+pub mod dynamic_tree;
+pub mod narrow_phase;
+pub mod response;
+pub mod world;
+
+pub use aabb::Aabb;
+pub use ball::Ball;
@@ -0,0 +1,160 @@
+use super::Ball;
+
+//広域当たり判定(AABBツリー)で接触の可能性があるとされたペアを、円同士として厳密に判定する
+//接触していれば、衝突応答で押し戻したり跳ね返したりするのに必要な情報(接触多様体)を返す
+
+//a,bはボールのid 応答の段階ではボールを書き換えるので参照ではなくidで持つ
+#[derive(Debug)]
+pub struct Contact {
+    pub a: i32,
+    pub b: i32,
+    //aからbへ向かう単位ベクトル bをこの向きにdepthだけ動かすと離れる
+    pub normal_x: f64,
+    pub normal_y: f64,
+    //めり込みの深さ
+    pub depth: f64,
+    //接触点 円同士は1点で接するので、めり込んだ部分の真ん中を取る
+    pub point_x: f64,
+    pub point_y: f64,
+}
+
+//2つの円が重なっていればContactを返す ちょうど接しているだけ(めり込み0)の場合は接触なしとする
+pub fn circle_contact(a: &Ball, b: &Ball) -> Option<Contact> {
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    let radius_sum = a.size + b.size;
+
+    //平方根を取る前に距離の2乗で弾いておく
+    let distance_squared = dx * dx + dy * dy;
+    if distance_squared >= radius_sum * radius_sum {
+        return None;
+    }
+
+    let distance = distance_squared.sqrt();
+
+    //中心が完全に重なっていると向きが決まらないので、とりあえずx軸の正の向きに押し出す
+    let (normal_x, normal_y) = if distance > f64::EPSILON {
+        (dx / distance, dy / distance)
+    } else {
+        (1.0, 0.0)
+    };
+    let depth = radius_sum - distance;
+
+    //aの表面から、めり込んだ深さの半分だけ内側の点
+    let reach = a.size - depth / 2.0;
+
+    Some(Contact {
+        a: a.id,
+        b: b.id,
+        normal_x,
+        normal_y,
+        depth,
+        point_x: a.x + normal_x * reach,
+        point_y: a.y + normal_y * reach,
+    })
+}
+
+//広域当たり判定の結果のペアを全部調べて、実際に接触しているものだけを返す
+pub fn find_contacts(pairs: &[(&Ball, &Ball)]) -> Vec<Contact> {
+    pairs
+        .iter()
+        .filter_map(|&(a, b)| circle_contact(a, b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ball(x: f64, y: f64, size: f64) -> Ball {
+        Ball::new(x, y, 0.0, 0.0, "rgb(0,0,0)", size, 0)
+    }
+
+    fn ball_with_id(x: f64, y: f64, size: f64, id: i32) -> Ball {
+        Ball::new(x, y, 0.0, 0.0, "rgb(0,0,0)", size, id)
+    }
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn separated_balls_have_no_contact() {
+        let a = ball(0.0, 0.0, 5.0);
+        let b = ball(20.0, 0.0, 5.0);
+        assert!(circle_contact(&a, &b).is_none());
+    }
+
+    #[test]
+    fn touching_balls_have_no_contact() {
+        let a = ball(0.0, 0.0, 5.0);
+        let b = ball(10.0, 0.0, 5.0);
+        assert!(circle_contact(&a, &b).is_none());
+    }
+
+    //AABBは重なるが円は重ならない(斜めに並んだ)ペアは広域当たり判定では残るので、ここで落とす
+    #[test]
+    fn diagonal_aabb_overlap_without_contact() {
+        let a = ball(0.0, 0.0, 5.0);
+        let b = ball(8.0, 8.0, 5.0);
+        assert!(a.aabb().is_intersects(&b.aabb()));
+        assert!(circle_contact(&a, &b).is_none());
+    }
+
+    #[test]
+    fn overlapping_balls() {
+        let a = ball(0.0, 0.0, 5.0);
+        let b = ball(8.0, 0.0, 4.0);
+        let contact = circle_contact(&a, &b).unwrap();
+
+        assert!(approx(contact.normal_x, 1.0));
+        assert!(approx(contact.normal_y, 0.0));
+        assert!(approx(contact.depth, 1.0));
+        //aの表面(x=5)とbの表面(x=4)の真ん中
+        assert!(approx(contact.point_x, 4.5));
+        assert!(approx(contact.point_y, 0.0));
+    }
+
+    #[test]
+    fn normal_points_from_a_to_b() {
+        let a = ball(10.0, 10.0, 5.0);
+        let b = ball(7.0, 6.0, 5.0);
+        let contact = circle_contact(&a, &b).unwrap();
+
+        assert!(approx(contact.normal_x, -0.6));
+        assert!(approx(contact.normal_y, -0.8));
+        assert!(approx(contact.depth, 5.0));
+        assert!(approx(contact.point_x, 10.0 - 0.6 * 2.5));
+        assert!(approx(contact.point_y, 10.0 - 0.8 * 2.5));
+
+        //逆向きに調べると法線も逆になる
+        let reversed = circle_contact(&b, &a).unwrap();
+        assert!(approx(reversed.normal_x, 0.6));
+        assert!(approx(reversed.normal_y, 0.8));
+        assert!(approx(reversed.depth, contact.depth));
+    }
+
+    #[test]
+    fn concentric_balls() {
+        let a = ball(3.0, 3.0, 5.0);
+        let b = ball(3.0, 3.0, 2.0);
+        let contact = circle_contact(&a, &b).unwrap();
+
+        assert!(approx(
+            contact.normal_x * contact.normal_x + contact.normal_y * contact.normal_y,
+            1.0
+        ));
+        assert!(approx(contact.depth, 7.0));
+    }
+
+    #[test]
+    fn find_contacts_keeps_only_touching_pairs() {
+        let a = ball_with_id(0.0, 0.0, 5.0, 0);
+        let b = ball_with_id(6.0, 0.0, 5.0, 1);
+        let c = ball_with_id(8.0, 8.0, 5.0, 2);
+        let contacts = find_contacts(&[(&a, &b), (&a, &c)]);
+
+        assert_eq!(contacts.len(), 1);
+        assert_eq!((contacts[0].a, contacts[0].b), (0, 1));
+    }
+}
@@ -0,0 +1,284 @@
+use std::f64::consts::PI;
+
+use super::narrow_phase::Contact;
+use super::Ball;
+
+//狭域当たり判定で見つかった接触に対して、撃力(インパルス)で速度を変えて跳ね返し、めり込んだ分を押し戻す
+//ボールは回転を持たないので、摩擦は接線方向の相対速度を弱めるだけにしている
+
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionSettings {
+    //反発係数 1で完全弾性衝突、0でくっつく
+    pub restitution: f64,
+    //動摩擦係数 接線方向の撃力は法線方向の撃力のfriction倍まで
+    pub friction: f64,
+    //めり込みを1回の補正で何割押し戻すか 1にすると積み重なったボールが震えやすい
+    pub correction_percent: f64,
+    //この深さまでのめり込みは許して押し戻さない(接触し続けているペアがガタつかないように)
+    pub slop: f64,
+}
+
+impl Default for CollisionSettings {
+    fn default() -> CollisionSettings {
+        CollisionSettings {
+            restitution: 1.0,
+            friction: 0.0,
+            correction_percent: 0.8,
+            slop: 0.01,
+        }
+    }
+}
+
+impl CollisionSettings {
+    //反発係数と摩擦係数を0〜1に収めて作る 1を超える反発係数はぶつかるたびにエネルギーが増えて発散する
+    //NaNは既定値にする
+    pub fn new(restitution: f64, friction: f64) -> CollisionSettings {
+        let default = CollisionSettings::default();
+        let unit = |value: f64, fallback: f64| {
+            if value.is_nan() {
+                fallback
+            } else {
+                value.clamp(0.0, 1.0)
+            }
+        };
+        CollisionSettings {
+            restitution: unit(restitution, default.restitution),
+            friction: unit(friction, default.friction),
+            ..default
+        }
+    }
+}
+
+impl Ball {
+    //質量は円の面積に比例させる(密度1)
+    pub fn mass(&self) -> f64 {
+        PI * self.size * self.size
+    }
+
+    fn inverse_mass(&self) -> f64 {
+        let mass = self.mass();
+        if mass > 0.0 {
+            1.0 / mass
+        } else {
+            0.0
+        }
+    }
+}
+
+//1つの接触を解決する contactはaからbへの法線を持つ
+pub fn resolve_contact(
+    a: &mut Ball,
+    b: &mut Ball,
+    contact: &Contact,
+    settings: &CollisionSettings,
+) {
+    let inverse_mass_a = a.inverse_mass();
+    let inverse_mass_b = b.inverse_mass();
+    let inverse_mass_sum = inverse_mass_a + inverse_mass_b;
+    if inverse_mass_sum <= 0.0 {
+        return;
+    }
+    let (normal_x, normal_y) = (contact.normal_x, contact.normal_y);
+
+    //めり込みの補正 軽い方が大きく動くように逆質量で配分する
+    let correction =
+        (contact.depth - settings.slop).max(0.0) / inverse_mass_sum * settings.correction_percent;
+    a.x -= normal_x * correction * inverse_mass_a;
+    a.y -= normal_y * correction * inverse_mass_a;
+    b.x += normal_x * correction * inverse_mass_b;
+    b.y += normal_y * correction * inverse_mass_b;
+
+    //aから見たbの相対速度
+    let relative_x = b.vel_x - a.vel_x;
+    let relative_y = b.vel_y - a.vel_y;
+    let velocity_along_normal = relative_x * normal_x + relative_y * normal_y;
+
+    //すでに離れる向きに動いているなら跳ね返さない(めり込みの補正だけ)
+    if velocity_along_normal > 0.0 {
+        return;
+    }
+
+    let impulse = -(1.0 + settings.restitution) * velocity_along_normal / inverse_mass_sum;
+    a.vel_x -= normal_x * impulse * inverse_mass_a;
+    a.vel_y -= normal_y * impulse * inverse_mass_a;
+    b.vel_x += normal_x * impulse * inverse_mass_b;
+    b.vel_y += normal_y * impulse * inverse_mass_b;
+
+    //摩擦 法線の撃力を加えたあとの相対速度から接線方向の成分を取り出して打ち消す向きに撃力を加える
+    let relative_x = b.vel_x - a.vel_x;
+    let relative_y = b.vel_y - a.vel_y;
+    let velocity_along_normal = relative_x * normal_x + relative_y * normal_y;
+    let tangent_x = relative_x - velocity_along_normal * normal_x;
+    let tangent_y = relative_y - velocity_along_normal * normal_y;
+    let tangent_length = (tangent_x * tangent_x + tangent_y * tangent_y).sqrt();
+    if tangent_length <= f64::EPSILON {
+        return;
+    }
+    let (tangent_x, tangent_y) = (tangent_x / tangent_length, tangent_y / tangent_length);
+
+    //クーロン摩擦 止めきるのに必要な撃力と、法線の撃力×摩擦係数の小さい方
+    let friction_impulse = (tangent_length / inverse_mass_sum).min(impulse * settings.friction);
+    a.vel_x += tangent_x * friction_impulse * inverse_mass_a;
+    a.vel_y += tangent_y * friction_impulse * inverse_mass_a;
+    b.vel_x -= tangent_x * friction_impulse * inverse_mass_b;
+    b.vel_y -= tangent_y * friction_impulse * inverse_mass_b;
+}
+
+//idが同じ番号のボールを2つ同時に可変で借りる balls_initでidは並び順と同じ番号にしてある
+fn pair_mut(balls: &mut [Ball], a: usize, b: usize) -> (&mut Ball, &mut Ball) {
+    if a < b {
+        let (left, right) = balls.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = balls.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+pub fn resolve_contacts(balls: &mut [Ball], contacts: &[Contact], settings: &CollisionSettings) {
+    for contact in contacts.iter() {
+        if contact.a == contact.b {
+            continue;
+        }
+        let (a, b) = pair_mut(balls, contact.a as usize, contact.b as usize);
+        resolve_contact(a, b, contact, settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::narrow_phase::circle_contact;
+
+    fn ball(x: f64, vel_x: f64, size: f64, id: i32) -> Ball {
+        Ball::new(x, 0.0, vel_x, 0.0, "rgb(0,0,0)", size, id)
+    }
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn momentum(balls: &[Ball]) -> (f64, f64) {
+        balls.iter().fold((0.0, 0.0), |(x, y), ball| {
+            (x + ball.mass() * ball.vel_x, y + ball.mass() * ball.vel_y)
+        })
+    }
+
+    fn energy(balls: &[Ball]) -> f64 {
+        balls
+            .iter()
+            .map(|ball| 0.5 * ball.mass() * (ball.vel_x * ball.vel_x + ball.vel_y * ball.vel_y))
+            .sum()
+    }
+
+    fn collide(balls: &mut [Ball], settings: &CollisionSettings) {
+        let contact = circle_contact(&balls[0], &balls[1]).unwrap();
+        resolve_contacts(balls, &[contact], settings);
+    }
+
+    //同じ質量の正面衝突では速度が入れ替わる
+    #[test]
+    fn equal_mass_head_on_swaps_velocities() {
+        let mut balls = vec![ball(0.0, 2.0, 5.0, 0), ball(9.0, -1.0, 5.0, 1)];
+        collide(&mut balls, &CollisionSettings::default());
+
+        assert!(approx(balls[0].vel_x, -1.0));
+        assert!(approx(balls[1].vel_x, 2.0));
+    }
+
+    #[test]
+    fn elastic_collision_conserves_momentum_and_energy() {
+        let mut balls = vec![
+            Ball::new(0.0, 0.0, 3.0, 1.0, "rgb(0,0,0)", 8.0, 0),
+            Ball::new(10.0, 4.0, -1.0, -2.0, "rgb(0,0,0)", 4.0, 1),
+        ];
+        let (px, py) = momentum(&balls);
+        let e = energy(&balls);
+
+        collide(&mut balls, &CollisionSettings::default());
+
+        let (qx, qy) = momentum(&balls);
+        assert!((px - qx).abs() < 1e-6 && (py - qy).abs() < 1e-6);
+        assert!((e - energy(&balls)).abs() < 1e-6);
+    }
+
+    //反発係数0なら法線方向の相対速度が0になる
+    #[test]
+    fn zero_restitution_stops_relative_motion() {
+        let settings = CollisionSettings {
+            restitution: 0.0,
+            ..CollisionSettings::default()
+        };
+        let mut balls = vec![ball(0.0, 2.0, 5.0, 0), ball(9.0, 0.0, 10.0, 1)];
+        let (px, _) = momentum(&balls);
+        collide(&mut balls, &settings);
+
+        assert!(approx(balls[0].vel_x, balls[1].vel_x));
+        assert!(approx(momentum(&balls).0, px));
+    }
+
+    //すでに離れていく向きに動いているペアは速度を変えない
+    #[test]
+    fn separating_balls_keep_velocity() {
+        let mut balls = vec![ball(0.0, -1.0, 5.0, 0), ball(9.0, 1.0, 5.0, 1)];
+        collide(&mut balls, &CollisionSettings::default());
+
+        assert!(approx(balls[0].vel_x, -1.0));
+        assert!(approx(balls[1].vel_x, 1.0));
+    }
+
+    //めり込みは質量の逆数の比で押し戻され、補正後はslop程度まで浅くなる
+    #[test]
+    fn positional_correction_separates_balls() {
+        let settings = CollisionSettings {
+            correction_percent: 1.0,
+            ..CollisionSettings::default()
+        };
+        let mut balls = vec![ball(0.0, 0.0, 10.0, 0), ball(10.0, 0.0, 5.0, 1)];
+        collide(&mut balls, &settings);
+
+        //大きい方(質量4倍)は小さい方の1/4しか動かない
+        assert!(approx(-balls[0].x * 4.0, balls[1].x - 10.0));
+        let depth = 15.0 - (balls[1].x - balls[0].x);
+        assert!(approx(depth, settings.slop));
+    }
+
+    //摩擦があると接線方向の相対速度が弱まる
+    #[test]
+    fn friction_reduces_tangential_velocity() {
+        let make = || {
+            vec![
+                Ball::new(0.0, 0.0, 1.0, 2.0, "rgb(0,0,0)", 5.0, 0),
+                Ball::new(9.0, 0.0, -1.0, -2.0, "rgb(0,0,0)", 5.0, 1),
+            ]
+        };
+        let mut frictionless = make();
+        collide(&mut frictionless, &CollisionSettings::default());
+        assert!(approx(frictionless[0].vel_y, 2.0));
+
+        let mut rough = make();
+        let settings = CollisionSettings {
+            friction: 0.5,
+            ..CollisionSettings::default()
+        };
+        collide(&mut rough, &settings);
+        assert!(rough[0].vel_y.abs() < 2.0);
+        assert!(rough[0].vel_y > 0.0);
+        assert!(approx(momentum(&rough).1, 0.0));
+    }
+
+    //反発係数と摩擦係数は0〜1に収められ、NaNは既定値になる
+    #[test]
+    fn new_clamps_coefficients_to_unit_range() {
+        let settings = CollisionSettings::new(1.5, -0.5);
+        assert_eq!(settings.restitution, 1.0);
+        assert_eq!(settings.friction, 0.0);
+
+        let settings = CollisionSettings::new(f64::NAN, 0.3);
+        assert_eq!(
+            settings.restitution,
+            CollisionSettings::default().restitution
+        );
+        assert_eq!(settings.friction, 0.3);
+    }
+}
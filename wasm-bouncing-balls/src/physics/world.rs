@@ -0,0 +1,435 @@
+use super::broad_phase::{BroadPhase, Dynamic};
+use super::ccd::{earliest_impact, resolve_impact};
+use super::narrow_phase::{find_contacts, Contact};
+use super::response::{resolve_contacts, CollisionSettings};
+use super::Ball;
+
+//物理計算をrequestAnimationFrameの間隔(=モニターのリフレッシュレート)から切り離すためのワールド
+//経過時間をアキュムレータに貯めて、決まった時間刻みで何回進めるかを決める
+//描画は最後の2ステップの間を補間するので、リフレッシュレートが時間刻みと合わなくてもカクつかない
+
+//物理の1ステップの時間(秒)
+pub const TIMESTEP: f64 = 1.0 / 60.0;
+
+//1フレームで進めるステップ数の上限 重くて追いつけないときに、ステップを重ねてさらに重くなるのを防ぐ
+const MAX_STEPS_PER_FRAME: u32 = 5;
+
+//1ステップの中で衝突時刻まで進めて跳ね返す回数の上限 超えた分は狭域当たり判定とkeep_insideに任せる
+const MAX_IMPACTS_PER_STEP: u32 = 32;
+
+pub struct World {
+    pub balls: Vec<Ball>,
+    pub width: f64,
+    pub height: f64,
+    //重力加速度(px/s^2)
+    pub gravity_x: f64,
+    pub gravity_y: f64,
+    pub settings: CollisionSettings,
+    //広域当たり判定の方式 ステップをまたいで持ち続けるので、動的ツリーや並び順は次のステップに引き継がれる
+    pub broad_phase: Box<dyn BroadPhase>,
+    //まだ進めていない時間(秒)
+    accumulator: f64,
+    //前回advanceが呼ばれたときのPerformance.now()(ミリ秒)
+    last_timestamp: Option<f64>,
+    //直近のステップで広域当たり判定に残ったペアと、実際の接触(デバッグ表示用) どちらもボールのidで持つ
+    pub pairs: Vec<(i32, i32)>,
+    pub contacts: Vec<Contact>,
+}
+
+impl World {
+    pub fn new(width: f64, height: f64) -> World {
+        World {
+            balls: vec![],
+            width,
+            height,
+            gravity_x: 0.0,
+            gravity_y: 0.0,
+            settings: CollisionSettings::default(),
+            broad_phase: Box::new(Dynamic::new()),
+            accumulator: 0.0,
+            last_timestamp: None,
+            pairs: vec![],
+            contacts: vec![],
+        }
+    }
+
+    //物理を1ステップ(TIMESTEP秒)進める
+    //半陰的オイラー法で先に速度を更新し、その速度で動く範囲を広域当たり判定にかける
+    //ステップの途中で起きる衝突は早い順に、その時刻まで全体を進めて跳ね返す(CCD)
+    pub fn step(&mut self) {
+        //広域当たり判定、CCD、接触の解決、デバッグ表示はどれもボールのidをballsの添字として使う
+        //idとballs上の位置がずれていると別のボールを跳ね返してしまうので、ここでまとめて確かめる
+        assert!(
+            self.balls
+                .iter()
+                .enumerate()
+                .all(|(i, ball)| ball.id == i as i32),
+            "ball ids must match their index in World::balls"
+        );
+
+        for ball in self.balls.iter_mut() {
+            ball.prev_x = ball.x;
+            ball.prev_y = ball.y;
+            ball.vel_x += self.gravity_x * TIMESTEP;
+            ball.vel_y += self.gravity_y * TIMESTEP;
+            ball.motion_x = ball.vel_x * TIMESTEP;
+            ball.motion_y = ball.vel_y * TIMESTEP;
+        }
+
+        let mut pairs = self.broad_phase.pairs(&self.balls);
+        //接触を解決する順番で結果が少し変わるので、どの広域当たり判定を使っても同じ順番にしておく
+        pairs.sort_unstable();
+
+        let mut remaining = TIMESTEP;
+        for _ in 0..MAX_IMPACTS_PER_STEP {
+            let (time, impact) =
+                match earliest_impact(&self.balls, &pairs, self.width, self.height, remaining) {
+                    Some(earliest) => earliest,
+                    None => break,
+                };
+            for ball in self.balls.iter_mut() {
+                ball.move_by(time);
+            }
+            remaining -= time;
+            resolve_impact(&mut self.balls, impact, &self.settings);
+        }
+
+        for ball in self.balls.iter_mut() {
+            ball.move_by(remaining);
+            ball.keep_inside(self.width, self.height);
+            ball.motion_x = 0.0;
+            ball.motion_y = 0.0;
+        }
+
+        //最初から重なっていたペアや上限を超えて残っためり込みは、いつもの狭域当たり判定で押し戻す
+        let ballrefs: Vec<(&Ball, &Ball)> = pairs
+            .iter()
+            .map(|&(a, b)| (&self.balls[a as usize], &self.balls[b as usize]))
+            .collect();
+        self.contacts = find_contacts(&ballrefs);
+        self.pairs = pairs;
+
+        resolve_contacts(&mut self.balls, &self.contacts, &self.settings);
+    }
+
+    //Performance.now()のタイムスタンプ(ミリ秒)を受け取って、前回からの経過時間分だけステップを進める 進めたステップ数を返す
+    pub fn advance(&mut self, now: f64) -> u32 {
+        let elapsed = match self.last_timestamp {
+            Some(last) => (now - last).max(0.0) / 1000.0,
+            None => 0.0,
+        };
+        self.last_timestamp = Some(now);
+
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= TIMESTEP && steps < MAX_STEPS_PER_FRAME {
+            self.step();
+            self.accumulator -= TIMESTEP;
+            steps += 1;
+        }
+
+        //上限で進めきれなかった分は1ステップに満たない端数だけ残して捨てる(遅れを取り戻そうとし続けないように)
+        if steps == MAX_STEPS_PER_FRAME {
+            self.accumulator %= TIMESTEP;
+        }
+        steps
+    }
+
+    //一時停止中の時間を経過時間に数えないように、次のadvanceを0秒から数え直す
+    pub fn stop_clock(&mut self) {
+        self.last_timestamp = None;
+    }
+
+    //描画の補間に使う、最後のステップから次のステップまでのどのあたりにいるか(0.0〜1.0)
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / TIMESTEP).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_with_ball(vel_x: f64) -> World {
+        let mut world = World::new(1000.0, 1000.0);
+        world
+            .balls
+            .push(Ball::new(100.0, 100.0, vel_x, 0.0, "rgb(0,0,0)", 5.0, 0));
+        world
+    }
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    //同じ1秒でも、フレームの間隔に関係なく同じ距離だけ進む
+    #[test]
+    fn distance_does_not_depend_on_frame_rate() {
+        let mut positions = vec![];
+        for &fps in &[30, 60, 144] {
+            let mut world = world_with_ball(60.0);
+            for i in 0..fps {
+                world.advance(i as f64 * 1000.0 / fps as f64);
+            }
+            //足し引きの丸め誤差で最後の1ステップを取りこぼさないように少しだけ過ぎた時刻で終える
+            world.advance(1000.5);
+            positions.push(world.balls[0].x);
+        }
+        for x in positions.iter() {
+            assert!(approx(*x, 160.0), "{:?}", positions);
+        }
+    }
+
+    #[test]
+    fn accumulator_carries_partial_steps() {
+        let mut world = world_with_ball(60.0);
+        assert_eq!(world.advance(0.0), 0);
+        //半ステップでは進まず、補間の割合だけが変わる
+        assert_eq!(world.advance(TIMESTEP * 500.0), 0);
+        assert!(approx(world.alpha(), 0.5));
+        assert_eq!(world.advance(TIMESTEP * 1000.0 + 0.01), 1);
+        assert!(world.alpha() < 0.01);
+        assert!(approx(world.balls[0].x, 101.0));
+    }
+
+    //長く止まっていても1フレームで進めるステップ数には上限がある
+    #[test]
+    fn steps_per_frame_are_capped() {
+        let mut world = world_with_ball(60.0);
+        world.advance(0.0);
+        assert_eq!(world.advance(10_000.0), MAX_STEPS_PER_FRAME);
+        assert!(world.alpha() <= 1.0);
+
+        world.stop_clock();
+        assert_eq!(world.advance(20_000.0), 0);
+    }
+
+    //半陰的オイラー法なので速度を先に更新し、その速度で位置を進める
+    #[test]
+    fn gravity_uses_semi_implicit_euler() {
+        let mut world = world_with_ball(0.0);
+        world.gravity_y = 600.0;
+        world.step();
+
+        let ball = &world.balls[0];
+        assert!(approx(ball.vel_y, 10.0));
+        assert!(approx(ball.y, 100.0 + 10.0 * TIMESTEP));
+        assert!(approx(ball.prev_y, 100.0));
+    }
+
+    #[test]
+    fn balls_bounce_off_walls() {
+        let mut world = world_with_ball(-600.0);
+        for _ in 0..20 {
+            world.step();
+        }
+        let ball = &world.balls[0];
+        assert!(ball.vel_x > 0.0);
+        assert!(ball.x - ball.size >= 0.0);
+    }
+
+    //1ステップで壁を越えるほど速くても、壁に当たった時刻で跳ね返った位置にいる
+    #[test]
+    fn fast_ball_bounces_at_time_of_impact() {
+        let mut world = World::new(1000.0, 1000.0);
+        world.balls.push(Ball::new(
+            900.0,
+            500.0,
+            600.0 / TIMESTEP,
+            0.0,
+            "rgb(0,0,0)",
+            5.0,
+            0,
+        ));
+        world.step();
+
+        //壁(x=995)まで95px進んで、残りの505px戻る
+        let ball = &world.balls[0];
+        assert!((ball.x - 490.0).abs() < 1e-6);
+        assert!(ball.vel_x < 0.0);
+    }
+
+    //ステップの始めと終わりだけ見ると重なっていない速くて小さいボール同士もすり抜けない
+    #[test]
+    fn fast_balls_do_not_tunnel_through_each_other() {
+        let mut world = World::new(1000.0, 1000.0);
+        world.balls.push(Ball::new(
+            400.0,
+            500.0,
+            100.0 / TIMESTEP,
+            0.0,
+            "rgb(0,0,0)",
+            2.0,
+            0,
+        ));
+        world.balls.push(Ball::new(
+            450.0,
+            500.0,
+            -100.0 / TIMESTEP,
+            0.0,
+            "rgb(0,0,0)",
+            2.0,
+            1,
+        ));
+        world.step();
+
+        let (a, b) = (&world.balls[0], &world.balls[1]);
+        assert!(a.x < b.x);
+        assert!(a.vel_x < 0.0 && b.vel_x > 0.0);
+        assert_eq!(world.pairs, vec![(0, 1)]);
+    }
+
+    //ボールを入れ直して数が変わってもツリーがついてくる
+    //world.pairsは実際のAABBが重なるペアだけなので、fat AABBだけが重なる間隔(11px)ではなく重なる間隔(9px)に並べる
+    #[test]
+    fn tree_follows_reinitialized_balls() {
+        let mut world = World::new(1000.0, 1000.0);
+        for i in 0..10 {
+            world.balls.push(Ball::new(
+                50.0 + i as f64 * 9.0,
+                100.0,
+                0.0,
+                0.0,
+                "rgb(0,0,0)",
+                5.0,
+                i,
+            ));
+        }
+        world.step();
+        assert_eq!(world.pairs.len(), 9);
+
+        world.balls.clear();
+        for i in 0..3 {
+            world.balls.push(Ball::new(
+                500.0,
+                100.0 + i as f64 * 100.0,
+                0.0,
+                0.0,
+                "rgb(0,0,0)",
+                5.0,
+                i,
+            ));
+        }
+        world.step();
+        assert!(world.pairs.is_empty());
+        //トレイト越しには葉とノードを区別できないので全部数える 葉が3つと、それをまとめるノードが2つ
+        let mut nodes = 0;
+        world.broad_phase.for_each_aabb(&mut |_| nodes += 1);
+        assert_eq!(nodes, 5);
+    }
+
+    //動的ツリー(fat AABB)のペアは、毎回作り直すツリーで見つかるペアを必ず含む
+    //BroadPhase::pairsはfat AABBのペアから実際に重なるものだけに絞るので、絞る前のtree.pairs()と比べる
+    #[test]
+    fn dynamic_tree_pairs_cover_rebuilt_tree_pairs() {
+        let mut world = World::new(400.0, 400.0);
+        for i in 0..200 {
+            let mut ball = Ball::new(
+                (i * 37 % 380) as f64 + 10.0,
+                (i * 91 % 380) as f64 + 10.0,
+                0.0,
+                0.0,
+                "rgb(0,0,0)",
+                4.0 + (i % 5) as f64,
+                i,
+            );
+            ball.motion_x = (i % 9) as f64 * 3.0 - 12.0;
+            ball.motion_y = (i % 7) as f64 * 4.0 - 12.0;
+            world.balls.push(ball);
+        }
+        let mut dynamic = Dynamic::new();
+        dynamic.update(&world.balls);
+        let tree_pairs = dynamic.tree.pairs();
+
+        for name in ["midpoint", "sah", "midpoint-dual", "sah-dual"].iter() {
+            let mut rebuilt = crate::physics::broad_phase::from_name(name).unwrap();
+            let pairs = rebuilt.pairs(&world.balls);
+            assert!(!pairs.is_empty());
+            for pair in pairs.iter() {
+                assert!(tree_pairs.contains(pair), "{} {:?}", name, pair);
+            }
+        }
+    }
+
+    //どの広域当たり判定を使っても同じように動く
+    #[test]
+    fn broad_phases_give_same_result() {
+        let run = |name: &str| {
+            let mut world = World::new(400.0, 400.0);
+            world.broad_phase = crate::physics::broad_phase::from_name(name).unwrap();
+            for i in 0..100 {
+                world.balls.push(Ball::new(
+                    (i * 37 % 380) as f64 + 10.0,
+                    (i * 91 % 380) as f64 + 10.0,
+                    (i % 7) as f64 * 20.0 - 60.0,
+                    (i % 5) as f64 * 20.0 - 40.0,
+                    "rgb(0,0,0)",
+                    6.0,
+                    i,
+                ));
+            }
+            for _ in 0..30 {
+                world.step();
+            }
+            world
+                .balls
+                .iter()
+                .map(|ball| (ball.x, ball.y))
+                .collect::<Vec<(f64, f64)>>()
+        };
+
+        let dynamic = run("dynamic");
+        for name in crate::physics::broad_phase::NAMES.iter() {
+            for (a, b) in dynamic.iter().zip(run(name).iter()) {
+                assert!((a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6);
+            }
+        }
+    }
+
+    //ブラウザなしでたくさんのボールを何秒分も動かしても、画面の外に出たり大きくめり込んだまま残ったりしない
+    #[test]
+    fn crowded_world_runs_headless() {
+        let mut world = World::new(400.0, 300.0);
+        world.gravity_y = 300.0;
+        for i in 0..150 {
+            world.balls.push(Ball::new(
+                (i * 37 % 360) as f64 + 20.0,
+                (i * 91 % 260) as f64 + 20.0,
+                (i % 11) as f64 * 40.0 - 200.0,
+                (i % 7) as f64 * 40.0 - 120.0,
+                "rgb(0,0,0)",
+                4.0 + (i % 4) as f64,
+                i,
+            ));
+        }
+        for frame in 0..600 {
+            world.advance(frame as f64 * 1000.0 / 60.0);
+        }
+
+        for ball in world.balls.iter() {
+            assert!(ball.x.is_finite() && ball.y.is_finite());
+            assert!(ball.x - ball.size >= 0.0 && ball.x + ball.size <= world.width);
+            assert!(ball.y - ball.size >= 0.0 && ball.y + ball.size <= world.height);
+        }
+        for contact in world.contacts.iter() {
+            assert!(contact.depth < 5.0, "{:?}", contact);
+        }
+    }
+
+    #[test]
+    fn colliding_balls_bounce_apart() {
+        let mut world = World::new(1000.0, 1000.0);
+        world
+            .balls
+            .push(Ball::new(100.0, 100.0, 60.0, 0.0, "rgb(0,0,0)", 5.0, 0));
+        world
+            .balls
+            .push(Ball::new(115.0, 100.0, -60.0, 0.0, "rgb(0,0,0)", 5.0, 1));
+        for _ in 0..10 {
+            world.step();
+        }
+        assert!(world.balls[0].vel_x < 0.0);
+        assert!(world.balls[1].vel_x > 0.0);
+        assert!(world.balls[1].x - world.balls[0].x >= 10.0 - 0.02);
+    }
+}
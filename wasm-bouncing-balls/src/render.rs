@@ -0,0 +1,107 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::physics::narrow_phase::Contact;
+use crate::physics::world::World;
+use crate::physics::{Aabb, Ball};
+
+//Worldをキャンバスに描く 物理の状態は読むだけで変えない
+//キャンバスと描画コンテキストは最初に1回だけ取ってきて持っておく
+pub struct Renderer {
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+}
+
+impl Renderer {
+    pub fn new(canvas: HtmlCanvasElement) -> Renderer {
+        let ctx = canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<CanvasRenderingContext2d>()
+            .unwrap();
+        Renderer { canvas, ctx }
+    }
+
+    pub fn draw(&self, world: &World) {
+        let ctx = &self.ctx;
+
+        ctx.set_fill_style_str("rgba(0,0,0,1)");
+        ctx.fill_rect(
+            0.0,
+            0.0,
+            self.canvas.width() as f64,
+            self.canvas.height() as f64,
+        );
+
+        //ここから下はデバッグ表示 直近のステップの位置で、ツリーと広域・狭域当たり判定の結果を描く
+        ctx.set_stroke_style_str("rgba(0.0,255.0,255.0,0.2)");
+        ctx.set_line_width(2.0);
+
+        world
+            .broad_phase
+            .for_each_aabb(&mut |aabb| draw_aabb(ctx, aabb));
+
+        ctx.set_stroke_style_str("rgba(255.0,0.0,0.0,1)");
+        ctx.set_line_width(4.0);
+
+        for &(a, b) in world.pairs.iter() {
+            let aabb = world.balls[a as usize]
+                .aabb()
+                .union(&world.balls[b as usize].aabb());
+            draw_aabb(ctx, &aabb);
+        }
+
+        ctx.set_stroke_style_str("rgba(255.0,255.0,0.0,1)");
+        ctx.set_line_width(2.0);
+
+        for contact in world.contacts.iter() {
+            draw_contact(ctx, contact);
+        }
+
+        //ボールは最後の2ステップの間を補間した位置に描く
+        let alpha = world.alpha();
+        for ball in world.balls.iter() {
+            draw_ball(ctx, ball, alpha);
+        }
+    }
+}
+
+//前のステップと今のステップの間をalpha(0.0〜1.0)で補間した位置に描く
+fn draw_ball(ctx: &CanvasRenderingContext2d, ball: &Ball, alpha: f64) {
+    let x = ball.prev_x + (ball.x - ball.prev_x) * alpha;
+    let y = ball.prev_y + (ball.y - ball.prev_y) * alpha;
+
+    ctx.begin_path();
+    ctx.set_fill_style_str(&ball.color);
+    ctx.arc(x, y, ball.size, 0.0, 2.0 * std::f64::consts::PI)
+        .unwrap();
+    ctx.fill();
+}
+
+fn draw_aabb(ctx: &CanvasRenderingContext2d, aabb: &Aabb) {
+    ctx.begin_path();
+
+    ctx.move_to(aabb.x_min, aabb.y_min);
+    ctx.line_to(aabb.x_max, aabb.y_min);
+    ctx.line_to(aabb.x_max, aabb.y_max);
+    ctx.line_to(aabb.x_min, aabb.y_max);
+    ctx.line_to(aabb.x_min, aabb.y_min);
+
+    ctx.stroke();
+}
+
+//接触点から法線の向きにめり込みの深さ分(見やすいように最低でも10px)の線を引く
+fn draw_contact(ctx: &CanvasRenderingContext2d, contact: &Contact) {
+    let length = contact.depth.max(10.0);
+
+    ctx.begin_path();
+
+    ctx.move_to(contact.point_x, contact.point_y);
+    ctx.line_to(
+        contact.point_x + contact.normal_x * length,
+        contact.point_y + contact.normal_y * length,
+    );
+
+    ctx.stroke();
+}
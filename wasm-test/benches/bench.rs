@@ -1,13 +1,137 @@
-#![feature(test)]
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-extern crate test;
-extern crate wasm_game_of_life;
+use wasm_game_of_life::{Automaton, GifOptions, Lenia, Pattern, RuleKind, Universe};
 
-#[bench]
-fn universe_ticks(b: &mut test::Bencher) {
-    let mut universe = wasm_game_of_life::Universe::new();
+//stableのcargo benchで動くベンチマーク 結果はtarget/criterion以下に残るので前回との比較もcriterionがやってくれる
+//盤面は毎回同じになるようにシードを固定した乱数で作る
 
-    b.iter(|| {
-        universe.tick();
+const SEED: u64 = 0x5eed;
+
+const GOSPER_GLIDER_GUN: &str = "#N Gosper glider gun
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!";
+
+//生きているセルの割合がdensityの盤面
+fn random_universe(width: u32, height: u32, density: f64) -> Universe {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut universe = Universe::with_size(width, height);
+    let alive: Vec<(u32, u32)> = (0..height)
+        .flat_map(|row| (0..width).map(move |col| (row, col)))
+        .filter(|_| rng.gen_bool(density))
+        .collect();
+    universe.set_cells(&alive);
+    universe
+}
+
+fn tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    for &(width, height) in &[(64, 64), (256, 256), (512, 256), (1024, 1024)] {
+        for &density in &[0.05, 0.25, 0.5] {
+            let mut universe = random_universe(width, height, density);
+            group.throughput(Throughput::Elements((width * height) as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}x{}", width, height), density),
+                &density,
+                |b, _| b.iter(|| universe.tick()),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn pattern_loading(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pattern");
+
+    group.bench_function("parse gosper glider gun", |b| {
+        b.iter(|| Pattern::parse(black_box(GOSPER_GLIDER_GUN)).unwrap())
     });
+
+    //大きいパターンは乱数の盤面を書き出したRLEで代用する
+    let rle = random_universe(256, 256, 0.25).rle();
+    group.throughput(Throughput::Bytes(rle.len() as u64));
+    group.bench_function("parse 256x256 soup", |b| {
+        b.iter(|| Pattern::parse(black_box(&rle)).unwrap())
+    });
+    group.bench_function("load 256x256 soup", |b| {
+        b.iter(|| {
+            let mut universe = Universe::with_size(512, 512);
+            universe.load_pattern(black_box(&rle), 128, 128).unwrap();
+            universe
+        })
+    });
+    group.finish();
+}
+
+fn rendering(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    let universe = random_universe(512, 256, 0.25);
+
+    let mut buffer = vec![0u8; 512 * 256];
+    group.bench_function("copy cells 512x256", |b| {
+        b.iter(|| universe.copy_cells_into(black_box(&mut buffer)).unwrap())
+    });
+    group.bench_function("text 512x256", |b| b.iter(|| universe.render()));
+
+    let options = GifOptions {
+        generations: 10,
+        cell_size: 2,
+        ..GifOptions::default()
+    };
+    let small = random_universe(128, 128, 0.25);
+    group.bench_function("gif 128x128 10 generations", |b| {
+        b.iter(|| small.encode_gif(black_box(&options)).unwrap())
+    });
+
+    let mut automaton = Automaton::new(RuleKind::Cyclic, 256, 256);
+    automaton.randomize();
+    group.bench_function("rgba cyclic 256x256", |b| {
+        b.iter(|| automaton.render_rgba())
+    });
+
+    let lenia = Lenia::new(128, 128);
+    group.bench_function("rgba lenia 128x128", |b| b.iter(|| lenia.render_rgba()));
+    group.finish();
+}
+
+//盤面の保存と復元 巻き戻し用の履歴はスナップショットの列として持つことになるので、その1件分の重さを見る
+fn history(c: &mut Criterion) {
+    let mut group = c.benchmark_group("history");
+    let universe = random_universe(512, 256, 0.25);
+    let snapshot = universe.to_snapshot();
+    let fragment = universe.to_url_fragment();
+
+    group.bench_function("snapshot 512x256", |b| b.iter(|| universe.to_snapshot()));
+    group.bench_function("restore 512x256", |b| {
+        b.iter(|| Universe::from_snapshot(black_box(&snapshot)).unwrap())
+    });
+    group.bench_function("url fragment 512x256", |b| {
+        b.iter(|| universe.to_url_fragment())
+    });
+    group.bench_function("parse url fragment 512x256", |b| {
+        b.iter(|| Universe::parse_url_fragment(black_box(&fragment)).unwrap())
+    });
+
+    //100世代分の履歴を記録する
+    group.bench_function("record 100 generations 512x256", |b| {
+        b.iter(|| {
+            let mut universe = universe.clone();
+            let mut history = Vec::with_capacity(100);
+            for _ in 0..100 {
+                history.push(universe.to_snapshot());
+                universe.tick();
+            }
+            history
+        })
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(30);
+    targets = tick, pattern_loading, rendering, history
 }
+criterion_main!(benches);
@@ -0,0 +1,190 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Pattern, Universe};
+
+//canvasをドラッグしてセルを描くためのブラシ
+//マウスの移動イベントは飛び飛びに来るので、前回の位置から今回の位置までをブレゼンハムの直線でつないで塗る
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushShape {
+    Square,
+    Circle,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushMode {
+    Draw,
+    Erase,
+}
+
+#[wasm_bindgen]
+pub struct Brush {
+    //ブラシの直径(セル数)
+    size: u32,
+    shape: BrushShape,
+    mode: BrushMode,
+    //パターンブラシ 設定されている間はsize,shapeの代わりにこのパターンを押す
+    pattern: Option<Pattern>,
+    //ドラッグ中の前回の位置
+    last: Option<(i32, i32)>,
+}
+
+//(r0,c0)から(r1,c1)までの直線上のセルを両端を含めて順に返す
+pub fn bresenham_line(r0: i32, c0: i32, r1: i32, c1: i32) -> Vec<(i32, i32)> {
+    let dr = (r1 - r0).abs();
+    let dc = -(c1 - c0).abs();
+    let step_r = if r0 < r1 { 1 } else { -1 };
+    let step_c = if c0 < c1 { 1 } else { -1 };
+
+    let mut points = vec![];
+    let (mut r, mut c) = (r0, c0);
+    let mut err = dr + dc;
+    loop {
+        points.push((r, c));
+        if r == r1 && c == c1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dc {
+            err += dc;
+            r += step_r;
+        }
+        if e2 <= dr {
+            err += dr;
+            c += step_c;
+        }
+    }
+    points
+}
+
+impl Brush {
+    //ブラシを(row,column)を中心に1回押す 盤面の端を越えた分は反対側に回り込む
+    pub fn stamp(&self, universe: &mut Universe, row: i32, column: i32) {
+        let state = match self.mode {
+            BrushMode::Draw => Cell::Alive,
+            BrushMode::Erase => Cell::Dead,
+        };
+        let height = universe.height as i32;
+        let width = universe.width as i32;
+        let mut paint = |r: i32, c: i32| {
+            let idx = universe.get_index(r.rem_euclid(height) as u32, c.rem_euclid(width) as u32);
+            universe.cells[idx] = state;
+        };
+
+        if let Some(pattern) = &self.pattern {
+            //パターンは中心が押した位置に来るように置く 消しゴムのときはパターンの形に消す
+            let top = row - pattern.height as i32 / 2;
+            let left = column - pattern.width as i32 / 2;
+            for &(r, c) in pattern.cells.iter() {
+                paint(top + r as i32, left + c as i32);
+            }
+            return;
+        }
+
+        //sizeが偶数のときは中心より左上側に1セル多く広がる
+        let size = self.size as i32;
+        let start = -(size / 2);
+        let radius = size as f64 / 2.0;
+        for dr in start..start + size {
+            for dc in start..start + size {
+                if self.shape == BrushShape::Circle {
+                    //セルの中心がブラシの円に入っているかで判定する
+                    let y = dr as f64 - start as f64 + 0.5 - radius;
+                    let x = dc as f64 - start as f64 + 0.5 - radius;
+                    if x * x + y * y > radius * radius {
+                        continue;
+                    }
+                }
+                paint(row + dr, column + dc);
+            }
+        }
+    }
+
+    //前回の位置から(row,column)まで直線で塗る
+    //パターンブラシは重なって潰れないように、直線上でもパターンの大きさおきにだけ押す
+    pub fn stroke_to(&mut self, universe: &mut Universe, row: i32, column: i32) {
+        let (r0, c0) = self.last.unwrap_or((row, column));
+        let spacing = match &self.pattern {
+            Some(pattern) => pattern.width.max(pattern.height).max(1) as usize,
+            None => 1,
+        };
+
+        for &(r, c) in bresenham_line(r0, c0, row, column).iter().step_by(spacing) {
+            self.stamp(universe, r, c);
+        }
+        self.last = Some((row, column));
+    }
+}
+
+#[wasm_bindgen]
+impl Brush {
+    pub fn new() -> Brush {
+        Brush {
+            size: 1,
+            shape: BrushShape::Square,
+            mode: BrushMode::Draw,
+            pattern: None,
+            last: None,
+        }
+    }
+
+    pub fn set_size(&mut self, size: u32) {
+        self.size = size.max(1);
+    }
+    pub fn set_shape(&mut self, shape: BrushShape) {
+        self.shape = shape;
+    }
+    pub fn set_mode(&mut self, mode: BrushMode) {
+        self.mode = mode;
+    }
+
+    //RLEかプレーンテキストのパターンをブラシにする
+    pub fn set_pattern(&mut self, text: &str) -> Result<(), JsValue> {
+        let pattern = Pattern::parse(text).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.pattern = Some(pattern);
+        Ok(())
+    }
+    pub fn clear_pattern(&mut self) {
+        self.pattern = None;
+    }
+
+    //mousedownで呼ぶ その位置に1回押してドラッグを始める
+    pub fn press(&mut self, universe: &mut Universe, row: u32, column: u32) {
+        self.last = None;
+        self.stroke_to(universe, row as i32, column as i32);
+    }
+
+    //mousemoveで呼ぶ 押している間だけ前回の位置から線を引く
+    pub fn drag(&mut self, universe: &mut Universe, row: u32, column: u32) {
+        if self.last.is_some() {
+            self.stroke_to(universe, row as i32, column as i32);
+        }
+    }
+
+    //mouseupで呼ぶ
+    pub fn release(&mut self) {
+        self.last = None;
+    }
+
+    //(row0,column0)から(row1,column1)まで直線を引く ドラッグとは関係なく使える
+    pub fn line(
+        &mut self,
+        universe: &mut Universe,
+        row0: u32,
+        column0: u32,
+        row1: u32,
+        column1: u32,
+    ) {
+        self.last = Some((row0 as i32, column0 as i32));
+        self.stroke_to(universe, row1 as i32, column1 as i32);
+        self.last = None;
+    }
+}
+
+impl Default for Brush {
+    fn default() -> Brush {
+        Brush::new()
+    }
+}
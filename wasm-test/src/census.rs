@@ -0,0 +1,145 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use rand::{rngs::StdRng, SeedableRng};
+use wasm_bindgen::prelude::*;
+
+use crate::objects::ObjectKind;
+use crate::Universe;
+
+//ランダムなスープをたくさん安定するまで動かして、残った物体(灰)の種類を数える
+//同じシードからは同じスープができるので、面白い物体が見つかったらシードから再現できる
+#[wasm_bindgen]
+pub struct Census {
+    //スープ(ランダムに埋める正方形)の一辺
+    soup_size: u32,
+    //スープを置く盤面の一辺 小さいと灰が端を回り込んで干渉しやすい
+    universe_size: u32,
+    //安定しなかったスープはこの世代で打ち切ってそのまま数える
+    max_generations: u32,
+    soups: u32,
+    counts: BTreeMap<String, u32>,
+}
+
+impl Census {
+    //盤面の状態を比べるためのハッシュ
+    fn state_hash(universe: &Universe) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for &cell in universe.get_cells() {
+            (cell as u8).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    //シードからスープを作って盤面の中央に置く
+    pub fn soup(&self, seed: u64) -> Universe {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let soup_cells =
+            Universe::generate_cells_with_rng(self.soup_size, self.soup_size, &mut rng);
+
+        let offset = (self.universe_size - self.soup_size) / 2;
+        let mut universe = Universe::with_size(self.universe_size, self.universe_size);
+        let alive: Vec<(u32, u32)> = soup_cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &cell)| cell == crate::Cell::Alive)
+            .map(|(i, _)| {
+                let i = i as u32;
+                (offset + i / self.soup_size, offset + i % self.soup_size)
+            })
+            .collect();
+        universe.set_cells(&alive);
+        universe
+    }
+
+    //スープを前に見た状態に戻るまで(=全体が周期的になるまで)動かす
+    pub fn stabilize(&self, universe: &mut Universe) {
+        let mut seen = HashMap::new();
+        while universe.generation() < self.max_generations as u64 {
+            if seen
+                .insert(Census::state_hash(universe), universe.generation())
+                .is_some()
+            {
+                return;
+            }
+            universe.tick();
+        }
+    }
+
+    pub fn run_soup(&mut self, seed: u64) {
+        let mut universe = self.soup(seed);
+        self.stabilize(&mut universe);
+
+        for (_, identity) in universe.separate_objects() {
+            *self.counts.entry(identity.code).or_insert(0) += 1;
+        }
+        self.soups += 1;
+    }
+
+    pub fn counts(&self) -> &BTreeMap<String, u32> {
+        &self.counts
+    }
+
+    fn count_kind(&self, kind: ObjectKind) -> u32 {
+        let prefix = match kind {
+            ObjectKind::StillLife => "xs",
+            ObjectKind::Oscillator => "xp",
+            ObjectKind::Spaceship => "xq",
+            ObjectKind::Unknown => "zz",
+        };
+        self.counts
+            .iter()
+            .filter(|(code, _)| code.starts_with(prefix))
+            .map(|(_, &count)| count)
+            .sum()
+    }
+}
+
+#[wasm_bindgen]
+impl Census {
+    pub fn new(soup_size: u32, universe_size: u32) -> Census {
+        Census {
+            soup_size,
+            universe_size: universe_size.max(soup_size),
+            max_generations: 4000,
+            soups: 0,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_max_generations(&mut self, max_generations: u32) {
+        self.max_generations = max_generations;
+    }
+
+    //first_seedから順にcount個のスープを調べる Web Workerから少しずつ呼べるように区切ってある
+    pub fn search(&mut self, first_seed: u32, count: u32) {
+        for seed in first_seed..first_seed.saturating_add(count) {
+            self.run_soup(seed as u64);
+        }
+    }
+
+    pub fn soups(&self) -> u32 {
+        self.soups
+    }
+    pub fn still_lifes(&self) -> u32 {
+        self.count_kind(ObjectKind::StillLife)
+    }
+    pub fn oscillators(&self) -> u32 {
+        self.count_kind(ObjectKind::Oscillator)
+    }
+    pub fn spaceships(&self) -> u32 {
+        self.count_kind(ObjectKind::Spaceship)
+    }
+
+    //"apgcode 個数"の行を多い順に並べたテキスト
+    pub fn report(&self) -> String {
+        let mut counts: Vec<(&String, &u32)> = self.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        counts
+            .iter()
+            .map(|(code, count)| format!("{} {}\n", code, count))
+            .collect()
+    }
+}
@@ -0,0 +1,134 @@
+use wasm_bindgen::prelude::*;
+
+use crate::objects::{Identity, ObjectKind};
+use crate::Universe;
+
+//盤面上の物体に名前を付けて、canvasに注釈を描けるように位置と一緒に返す
+
+//よく見かける物体のapgcodeと名前の対応表
+const KNOWN_OBJECTS: &[(&str, &str)] = &[
+    ("xs4_33", "block"),
+    ("xs4_252", "tub"),
+    ("xs5_253", "boat"),
+    ("xs6_356", "ship"),
+    ("xs6_696", "beehive"),
+    ("xs6_25a4", "barge"),
+    ("xs7_2596", "loaf"),
+    ("xs7_25ac", "long boat"),
+    ("xs8_6996", "pond"),
+    ("xp2_7", "blinker"),
+    ("xp2_7e", "toad"),
+    ("xp2_318c", "beacon"),
+    ("xq4_153", "glider"),
+    ("xq4_6frc", "lightweight spaceship"),
+    ("xq4_27dee6", "middleweight spaceship"),
+    ("xq4_27deee6", "heavyweight spaceship"),
+];
+
+//1周期の移動量から進行方向を"N","SE"のような方角で表す 行は下向きが正なのでdyが負なら北
+fn heading(dx: i32, dy: i32) -> String {
+    let mut heading = String::new();
+    if dy < 0 {
+        heading.push('N');
+    } else if dy > 0 {
+        heading.push('S');
+    }
+    if dx > 0 {
+        heading.push('E');
+    } else if dx < 0 {
+        heading.push('W');
+    }
+    heading
+}
+
+fn object_name(identity: &Identity) -> String {
+    let name = KNOWN_OBJECTS
+        .iter()
+        .find(|(code, _)| *code == identity.code)
+        .map(|(_, name)| name.to_string());
+
+    let name = match (name, identity.kind) {
+        (Some(name), _) => name,
+        (None, ObjectKind::StillLife) => String::from("still life"),
+        (None, ObjectKind::Oscillator) => format!("p{} oscillator", identity.period),
+        (None, ObjectKind::Spaceship) => format!("c/{} spaceship", identity.period),
+        (None, ObjectKind::Unknown) => String::from("unknown"),
+    };
+
+    if identity.kind == ObjectKind::Spaceship {
+        format!("{} heading {}", name, heading(identity.dx, identity.dy))
+    } else {
+        name
+    }
+}
+
+//classifyの結果の1件 row,columnは外接矩形の左上のセル
+#[wasm_bindgen]
+pub struct ClassifiedObject {
+    row: u32,
+    column: u32,
+    width: u32,
+    height: u32,
+    kind: ObjectKind,
+    code: String,
+    name: String,
+    period: u32,
+    dx: i32,
+    dy: i32,
+}
+
+#[wasm_bindgen]
+impl ClassifiedObject {
+    pub fn row(&self) -> u32 {
+        self.row
+    }
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+    pub fn code(&self) -> String {
+        self.code.clone()
+    }
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+    pub fn dx(&self) -> i32 {
+        self.dx
+    }
+    pub fn dy(&self) -> i32 {
+        self.dy
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    //盤面の生きているセルを物体ごとに分けて、それぞれに名前と周期、移動量を付ける
+    pub fn classify(&self) -> Vec<ClassifiedObject> {
+        self.separate_objects()
+            .into_iter()
+            .map(|(island, identity)| ClassifiedObject {
+                row: island.row,
+                column: island.column,
+                width: island.width,
+                height: island.height,
+                kind: identity.kind,
+                name: object_name(&identity),
+                code: identity.code,
+                period: identity.period,
+                dx: identity.dx,
+                dy: identity.dy,
+            })
+            .collect()
+    }
+}
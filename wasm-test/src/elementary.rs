@@ -0,0 +1,178 @@
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+use crate::Cell;
+
+//Wolframの1次元セルオートマトン(Rule 30, Rule 110など)
+//1行ずつの世代を上から下へ積み重ねた時空図を、Universeと同じ幅×高さのCell配列として持つ
+//下まで埋まったら1行ずつ上にスクロールするので、js側はUniverseと同じようにcells()のポインタから描画できる
+#[wasm_bindgen]
+pub struct Elementary {
+    rule: u8,
+    width: u32,
+    height: u32,
+    cells: Vec<Cell>,
+    delta: Vec<Cell>,
+    //tickで次の行を作るための作業用の配列 毎回確保しないように使い回す
+    next: Vec<Cell>,
+    //時空図に書き込み済みの行数 heightに達するとスクロールが始まる
+    rows: u32,
+    generation: u64,
+}
+
+impl Elementary {
+    //最後に書き込んだ行(=現在の世代)
+    fn current_row(&self) -> &[Cell] {
+        let start = crate::get_index(self.width, self.rows - 1, 0);
+        &self.cells[start..start + self.width as usize]
+    }
+
+    //左・自分・右の3セルの組み合わせ(0〜7)をルール番号のビットで引いて次の状態を決める 端は反対側とつながっている
+    pub fn next_row(&self) -> Vec<Cell> {
+        let mut next = Vec::with_capacity(self.width as usize);
+        self.write_next_row(&mut next);
+        next
+    }
+
+    //next_rowと同じものを、渡された配列を使い回して書き込む
+    fn write_next_row(&self, next: &mut Vec<Cell>) {
+        let row = self.current_row();
+        let width = row.len();
+
+        next.clear();
+        next.extend((0..width).map(|i| {
+            let left = row[(i + width - 1) % width] as u8;
+            let center = row[i] as u8;
+            let right = row[(i + 1) % width] as u8;
+            let pattern = left << 2 | center << 1 | right;
+
+            if self.rule >> pattern & 1 == 1 {
+                Cell::Alive
+            } else {
+                Cell::Dead
+            }
+        }));
+    }
+
+    pub fn get_cells(&self) -> &[Cell] {
+        &self.cells
+    }
+    pub fn get_delta(&self) -> &[Cell] {
+        &self.delta
+    }
+
+    //1行目だけを与えられた状態にして時空図を描き直す
+    fn restart(&mut self, first_row: Vec<Cell>) {
+        //js側のビューが無効にならないように、配列は作り直さずに書き換える
+        self.cells.fill(Cell::Dead);
+        self.cells[..self.width as usize].copy_from_slice(&first_row);
+        //描き直しなのでdeltaは全セル変化ありにしておく
+        self.delta.fill(Cell::Alive);
+        self.rows = 1;
+        self.generation = 0;
+    }
+}
+
+#[wasm_bindgen]
+impl Elementary {
+    pub fn new(rule: u8, width: u32, height: u32) -> Elementary {
+        crate::utils::set_panic_hook();
+
+        //幅か高さが0だとresetで中央のセルを置けず、next_rowも0で割ることになるので最低1にする
+        let width = width.max(1);
+        let height = height.max(1);
+
+        let mut elementary = Elementary {
+            rule,
+            width,
+            height,
+            cells: vec![Cell::Dead; (width * height) as usize],
+            delta: vec![Cell::Alive; (width * height) as usize],
+            next: Vec::with_capacity(width as usize),
+            rows: 0,
+            generation: 0,
+        };
+        elementary.reset();
+        elementary
+    }
+
+    //Universe::tickと同じく、cells,deltaは作り直さずに書き換える deltaはAliveが変化ありを表す
+    pub fn tick(&mut self) {
+        let mut next = std::mem::take(&mut self.next);
+        self.write_next_row(&mut next);
+        let width = self.width as usize;
+        let changed = |a: Cell, b: Cell| if a != b { Cell::Alive } else { Cell::Dead };
+
+        if self.rows < self.height {
+            //空いている行(すべてDead)に書き足すだけなので、変わるのはその行だけ
+            self.rows += 1;
+            self.delta.fill(Cell::Dead);
+        } else {
+            //一番上の行を捨てて全体を1行上にずらす 各セルにはずらす前の1行下のセルが来る
+            let len = self.cells.len();
+            for i in 0..len - width {
+                self.delta[i] = changed(self.cells[i], self.cells[i + width]);
+            }
+            self.cells.copy_within(width.., 0);
+        }
+        //最後の行はずらしても元のままなので、そこに書く前の値と比べる
+        let start = crate::get_index(self.width, self.rows - 1, 0);
+        for (i, &cell) in next.iter().enumerate() {
+            self.delta[start + i] = changed(self.cells[start + i], cell);
+            self.cells[start + i] = cell;
+        }
+
+        self.next = next;
+        self.generation += 1;
+    }
+
+    //中央のセル1つだけが生きている状態から描き直す
+    pub fn reset(&mut self) {
+        let mut first_row = vec![Cell::Dead; self.width as usize];
+        first_row[self.width as usize / 2] = Cell::Alive;
+        self.restart(first_row);
+    }
+
+    //ランダムな1行目から描き直す
+    pub fn randomize(&mut self) {
+        let mut rng = rand::thread_rng();
+        let first_row = (0..self.width)
+            .map(|_i| {
+                if rng.gen_bool(0.5) {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            })
+            .collect();
+        self.restart(first_row);
+    }
+
+    pub fn rule(&self) -> u8 {
+        self.rule
+    }
+    //ルールを変えたら今の状態を1行目にして描き直す
+    pub fn set_rule(&mut self, rule: u8) {
+        self.rule = rule;
+        let current = self.current_row().to_vec();
+        self.restart(current);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    //Universe::cells,deltaと同じ形式のポインタ
+    pub fn cells(&self) -> *const Cell {
+        self.cells.as_ptr()
+    }
+    pub fn delta(&self) -> *const Cell {
+        self.delta.as_ptr()
+    }
+}
@@ -0,0 +1,180 @@
+use core::fmt;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use gif::{Encoder, Frame, Repeat};
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+//シミュレーションの経過をアニメーションGIFに書き出すエンコーダー
+//元のUniverseを進めてしまわないように、複製したUniverseでtickしながら1世代1フレームで記録する
+
+//記録できる世代数の上限 出来上がるGIFはフレーム数に比例して大きくなるので、jsから大きな値を渡されても際限なく使わないように
+pub const MAX_GIF_GENERATIONS: u32 = 1000;
+
+//全フレーム合わせた画素数(拡大後の幅×高さ×フレーム数)の上限 1フレームごとにこの分の画素列を作ってエンコードするので、
+//大きなUniverseでwasmのメモリや時間を使い切らないようにする 512x256を4ピクセル四方で60世代(約1.3億画素)までは収まる
+pub const MAX_GIF_PIXELS: u64 = 1 << 27;
+
+pub struct GifOptions {
+    //記録する世代数 最初の状態も1フレームとして含むのでフレーム数はgenerations + 1になる
+    pub generations: u32,
+    //1セルを何ピクセル四方で描くか
+    pub cell_size: u32,
+    //1フレームの表示時間(1/100秒単位)
+    pub delay: u16,
+    pub dead_color: [u8; 3],
+    pub alive_color: [u8; 3],
+}
+
+impl Default for GifOptions {
+    //www/index.jsのDEAD_COLOR,ALIVE_COLORに合わせて白地に黒
+    fn default() -> GifOptions {
+        GifOptions {
+            generations: 60,
+            cell_size: 4,
+            delay: 5,
+            dead_color: [0xff, 0xff, 0xff],
+            alive_color: [0x00, 0x00, 0x00],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GifError {
+    //GIFの幅と高さはu16までしか持てない
+    TooLarge,
+    //幅か高さが0のUniverseは画像にできない
+    Empty,
+    //generationsがMAX_GIF_GENERATIONSを超えている
+    TooManyGenerations,
+    //拡大後の幅×高さ×フレーム数がMAX_GIF_PIXELSを超えている
+    TooManyPixels,
+    Encode(String),
+}
+
+impl fmt::Display for GifError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GifError::TooLarge => write!(f, "gif: image is too large"),
+            GifError::Empty => write!(f, "gif: universe has no cells"),
+            GifError::TooManyGenerations => write!(
+                f,
+                "gif: at most {} generations can be recorded",
+                MAX_GIF_GENERATIONS
+            ),
+            GifError::TooManyPixels => write!(
+                f,
+                "gif: at most {} pixels can be recorded across all frames",
+                MAX_GIF_PIXELS
+            ),
+            GifError::Encode(e) => write!(f, "gif: {}", e),
+        }
+    }
+}
+
+impl From<gif::EncodingError> for GifError {
+    fn from(e: gif::EncodingError) -> GifError {
+        GifError::Encode(e.to_string())
+    }
+}
+impl From<std::io::Error> for GifError {
+    fn from(e: std::io::Error) -> GifError {
+        GifError::Encode(e.to_string())
+    }
+}
+
+impl Universe {
+    //セルを1セルcell_size四方に拡大した、パレット番号(0:Dead,1:Alive)の画素列を作る
+    fn gif_frame_pixels(&self, cell_size: usize) -> Vec<u8> {
+        let image_width = self.width as usize * cell_size;
+        let mut pixels = Vec::with_capacity(image_width * self.height as usize * cell_size);
+
+        for line in self.cells.chunks(self.width as usize) {
+            let start = pixels.len();
+            for &cell in line {
+                let index = (cell == Cell::Alive) as u8;
+                pixels.resize(pixels.len() + cell_size, index);
+            }
+            //同じ行をcell_size - 1回複製して縦方向にも拡大する
+            for _ in 1..cell_size {
+                pixels.extend_from_within(start..start + image_width);
+            }
+        }
+        pixels
+    }
+
+    pub fn encode_gif(&self, options: &GifOptions) -> Result<Vec<u8>, GifError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(GifError::Empty);
+        }
+        if options.generations > MAX_GIF_GENERATIONS {
+            return Err(GifError::TooManyGenerations);
+        }
+
+        let cell_size = options.cell_size.max(1);
+        let to_u16 = |len: u32| {
+            len.checked_mul(cell_size)
+                .and_then(|len| u16::try_from(len).ok())
+                .ok_or(GifError::TooLarge)
+        };
+        let image_width = to_u16(self.width)?;
+        let image_height = to_u16(self.height)?;
+        let frames = options.generations as u64 + 1;
+        if image_width as u64 * image_height as u64 * frames > MAX_GIF_PIXELS {
+            return Err(GifError::TooManyPixels);
+        }
+
+        let mut palette = vec![];
+        palette.extend_from_slice(&options.dead_color);
+        palette.extend_from_slice(&options.alive_color);
+
+        let mut encoder = Encoder::new(vec![], image_width, image_height, &palette)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let mut universe = self.clone();
+        for generation in 0..=options.generations {
+            if generation > 0 {
+                universe.tick();
+            }
+
+            let frame = Frame {
+                width: image_width,
+                height: image_height,
+                delay: options.delay,
+                buffer: Cow::Owned(universe.gif_frame_pixels(cell_size as usize)),
+                ..Frame::default()
+            };
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(encoder.into_inner()?)
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    //js側からはUint8Arrayとして受け取り、Blobにすればそのままダウンロードや<img>表示に使える
+    //色は0xRRGGBBの数値で渡す
+    pub fn to_gif(
+        &self,
+        generations: u32,
+        cell_size: u32,
+        delay: u16,
+        dead_color: u32,
+        alive_color: u32,
+    ) -> Result<Vec<u8>, JsValue> {
+        let rgb = |color: u32| [(color >> 16) as u8, (color >> 8) as u8, color as u8];
+        let options = GifOptions {
+            generations,
+            cell_size,
+            delay,
+            dead_color: rgb(dead_color),
+            alive_color: rgb(alive_color),
+        };
+
+        self.encode_gif(&options)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
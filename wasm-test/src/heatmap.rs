@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+
+use js_sys::Uint32Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Simulation, Universe};
+
+//セルごとに直近N世代で何回変化したかを数えて、ヒートマップとして描く
+//振動子のあるところは毎世代変化するので赤く、固定物体や何もない領域は変化しないので元のセルの色のまま残る
+
+//enable_activityで指定できる窓の上限 履歴は変化したセルの添字だけだが、大きすぎる窓で溜め込み続けないようにする
+pub const MAX_ACTIVITY_WINDOW: u32 = 1024;
+
+#[derive(Clone)]
+pub(crate) struct Activity {
+    //何世代分数えるか
+    window: u32,
+    //セルごとの直近window世代の変化回数
+    counts: Vec<u32>,
+    //直近window世代分の、その世代で変化したセルの添字 窓から外れたものを古い順に引いていく
+    //盤面全体を世代ごとに持つと窓の長さ×セル数になるので、変化したセルだけ覚えておく
+    history: VecDeque<Vec<u32>>,
+}
+
+impl Activity {
+    fn new(window: u32, len: usize) -> Activity {
+        Activity {
+            window,
+            counts: vec![0; len],
+            history: VecDeque::with_capacity(window as usize),
+        }
+    }
+
+    //tickのたびに呼ぶ deltaはAliveが変化ありを表す
+    pub(crate) fn record(&mut self, delta: &[Cell]) {
+        //窓がいっぱいなら一番古い世代を引いて、その配列を使い回す
+        let mut latest = if self.history.len() >= self.window as usize {
+            let oldest = self.history.pop_front().unwrap();
+            for &i in oldest.iter() {
+                self.counts[i as usize] -= 1;
+            }
+            oldest
+        } else {
+            Vec::new()
+        };
+
+        latest.clear();
+        latest.extend(
+            delta
+                .iter()
+                .enumerate()
+                .filter(|(_, &changed)| changed == Cell::Alive)
+                .map(|(i, _)| i as u32),
+        );
+        for &i in latest.iter() {
+            self.counts[i as usize] += 1;
+        }
+        self.history.push_back(latest);
+    }
+}
+
+//変化の割合(0.0〜1.0)を青→黄→赤のグラデーションにする
+fn heat_color(heat: f64) -> [u8; 3] {
+    let lerp = |a: f64, b: f64, t: f64| (a + (b - a) * t) as u8;
+    if heat < 0.5 {
+        let t = heat * 2.0;
+        [
+            lerp(32.0, 255.0, t),
+            lerp(64.0, 224.0, t),
+            lerp(255.0, 0.0, t),
+        ]
+    } else {
+        let t = (heat - 0.5) * 2.0;
+        [255, lerp(224.0, 0.0, t), 0]
+    }
+}
+
+impl Universe {
+    //盤面の大きさが変わったりresetされたときは数え直す
+    pub(crate) fn clear_activity(&mut self) {
+        if let Some(activity) = &self.activity {
+            self.activity = Some(Activity::new(activity.window, self.cells.len()));
+        }
+    }
+
+    pub fn get_activity(&self) -> Option<&[u32]> {
+        self.activity
+            .as_ref()
+            .map(|activity| activity.counts.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    //直近window世代の変化回数を数え始める すでに数えていた分は捨てる windowは1〜MAX_ACTIVITY_WINDOWに丸める
+    //cells,deltaは作り直さないのでversionは変えない
+    pub fn enable_activity(&mut self, window: u32) {
+        self.activity = Some(Activity::new(
+            window.clamp(1, MAX_ACTIVITY_WINDOW),
+            self.cells.len(),
+        ));
+    }
+    pub fn disable_activity(&mut self) {
+        self.activity = None;
+    }
+    //数えていないときは0
+    pub fn activity_window(&self) -> u32 {
+        self.activity.as_ref().map_or(0, |activity| activity.window)
+    }
+
+    //セルごとの変化回数 cells_viewと同じく次にRust側でメモリ確保が起きるまでしか有効でない 数えていないときは空
+    pub fn activity_view(&self) -> Uint32Array {
+        match self.get_activity() {
+            Some(counts) => unsafe { Uint32Array::view(counts) },
+            None => Uint32Array::new_with_length(0),
+        }
+    }
+
+    //ヒートマップのImageData用RGBA配列(1セル1ピクセル)
+    //一度も変化していないセルは普段の白黒で、変化したセルは窓の中で変化した割合に応じて青から赤で塗る
+    pub fn render_heatmap_rgba(&self) -> Vec<u8> {
+        let counts = self.get_activity();
+        let window = self.activity_window() as f64;
+
+        let mut rgba = Vec::with_capacity(self.cells.len() * 4);
+        for (i, &cell) in self.cells.iter().enumerate() {
+            let count = counts.map_or(0, |counts| counts[i]);
+            let color = if count > 0 {
+                heat_color(count as f64 / window)
+            } else if cell == Cell::Alive {
+                [0x00, 0x00, 0x00]
+            } else {
+                [0xff, 0xff, 0xff]
+            };
+            rgba.extend_from_slice(&color);
+            rgba.push(255);
+        }
+        rgba
+    }
+}
+
+#[wasm_bindgen]
+impl Simulation {
+    pub fn enable_activity(&mut self, window: u32) {
+        self.with_universe_mut(|universe| universe.enable_activity(window));
+    }
+    pub fn disable_activity(&mut self) {
+        self.with_universe_mut(|universe| universe.disable_activity());
+    }
+    pub fn render_heatmap_rgba(&self) -> Vec<u8> {
+        self.with_universe(|universe| universe.render_heatmap_rgba())
+    }
+}
@@ -0,0 +1,184 @@
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+use crate::Timer;
+
+//Lenia(連続値版のライフゲーム)
+//セルは0.0〜1.0のf32を持ち、リング状のカーネルで畳み込んだ近傍の値を成長関数に通して少しずつ状態を更新していく
+#[wasm_bindgen]
+pub struct Lenia {
+    width: u32,
+    height: u32,
+    cells: Vec<f32>,
+    next: Vec<f32>,
+    //畳み込みに使うカーネル (行方向のずれ,列方向のずれ,重み) の組を正規化済みで持っておく
+    kernel: Vec<(i32, i32, f32)>,
+    radius: u32,
+    mu: f32,
+    sigma: f32,
+    dt: f32,
+}
+
+impl Lenia {
+    //Universeと同じ並び
+    fn get_index(&self, row: u32, column: u32) -> usize {
+        crate::get_index(self.width, row, column)
+    }
+
+    //半径radiusのリング状カーネルを作る 中心からの距離rを0〜1に正規化し、exp(4 - 1/(r(1-r)))の山型で重みをつける
+    fn build_kernel(radius: u32) -> Vec<(i32, i32, f32)> {
+        let r = radius as i32;
+        let mut kernel = vec![];
+        let mut sum = 0.0;
+
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let dist = ((dx * dx + dy * dy) as f32).sqrt() / radius as f32;
+                if dist <= 0.0 || dist >= 1.0 {
+                    continue;
+                }
+                let weight = (4.0 - 1.0 / (dist * (1.0 - dist))).exp();
+                sum += weight;
+                kernel.push((dy, dx, weight));
+            }
+        }
+
+        //重みの合計が1になるように正規化しておくと畳み込み結果がそのまま0〜1に収まる
+        for k in kernel.iter_mut() {
+            k.2 /= sum;
+        }
+        kernel
+    }
+
+    //カーネルで畳み込んだ周囲の値(ポテンシャル)を求める 端はUniverseと同じくトーラス状につながっている
+    fn potential(&self, row: u32, column: u32) -> f32 {
+        let width = self.width as i32;
+        let height = self.height as i32;
+        let mut sum = 0.0;
+
+        for &(dy, dx, weight) in self.kernel.iter() {
+            let r = (row as i32 + dy).rem_euclid(height) as u32;
+            let c = (column as i32 + dx).rem_euclid(width) as u32;
+            sum += self.cells[self.get_index(r, c)] * weight;
+        }
+        sum
+    }
+
+    //成長関数 ポテンシャルがmu付近なら増え、離れるほど減る(-1.0〜1.0)
+    fn growth(&self, potential: f32) -> f32 {
+        let d = potential - self.mu;
+        2.0 * (-(d * d) / (2.0 * self.sigma * self.sigma)).exp() - 1.0
+    }
+
+    pub fn get_cells(&self) -> &[f32] {
+        &self.cells
+    }
+
+    //中央付近にランダムな塊を置いた初期状態を作る 全面ランダムだとすぐ飽和してしまうので
+    pub fn generate_init_cells(width: u32, height: u32, radius: u32) -> Vec<f32> {
+        let mut cells = vec![0.0; (width * height) as usize];
+        let mut rng = rand::thread_rng();
+
+        let blob = (radius * 2).min(width).min(height);
+        let top = (height - blob) / 2;
+        let left = (width - blob) / 2;
+
+        for row in top..top + blob {
+            for col in left..left + blob {
+                cells[crate::get_index(width, row, col)] = rng.gen_range(0.0..1.0);
+            }
+        }
+        cells
+    }
+}
+
+#[wasm_bindgen]
+impl Lenia {
+    pub fn new(width: u32, height: u32) -> Lenia {
+        crate::utils::set_panic_hook();
+
+        let radius = 13;
+        let cells = Lenia::generate_init_cells(width, height, radius);
+        let next = cells.clone();
+
+        Lenia {
+            width,
+            height,
+            cells,
+            next,
+            kernel: Lenia::build_kernel(radius),
+            radius,
+            mu: 0.15,
+            sigma: 0.015,
+            dt: 0.1,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        let _timer = Timer::new("Lenia::tick");
+
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let index = self.get_index(row, col);
+                let growth = self.growth(self.potential(row, col));
+
+                //dtずつ成長させて0〜1にクランプする
+                self.next[index] = (self.cells[index] + self.dt * growth).clamp(0.0, 1.0);
+            }
+        }
+
+        self.cells.swap_with_slice(&mut self.next);
+    }
+
+    pub fn reset(&mut self) {
+        self.cells = Lenia::generate_init_cells(self.width, self.height, self.radius);
+        self.next = self.cells.clone();
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+    //半径を変えたらカーネルを作り直す
+    pub fn set_radius(&mut self, radius: u32) {
+        self.radius = radius.max(1);
+        self.kernel = Lenia::build_kernel(self.radius);
+    }
+
+    pub fn set_mu(&mut self, mu: f32) {
+        self.mu = mu;
+    }
+    pub fn set_sigma(&mut self, sigma: f32) {
+        self.sigma = sigma;
+    }
+    pub fn set_dt(&mut self, dt: f32) {
+        self.dt = dt;
+    }
+
+    //f32のセル配列の先頭ポインタ js側ではFloat32Arrayで読む
+    pub fn cells(&self) -> *const f32 {
+        self.cells.as_ptr()
+    }
+
+    pub fn set_cell(&mut self, row: u32, column: u32, value: f32) {
+        let idx = self.get_index(row, column);
+        self.cells[idx] = value.clamp(0.0, 1.0);
+    }
+
+    //canvasのImageDataにそのまま渡せるRGBA配列(1セル1ピクセル)を作る
+    //ライフゲームの描画に合わせて0.0が白、1.0が黒になるようにしている
+    pub fn render_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.cells.len() * 4);
+        for &value in self.cells.iter() {
+            let shade = ((1.0 - value) * 255.0) as u8;
+            rgba.extend_from_slice(&[shade, shade, shade, 255]);
+        }
+        rgba
+    }
+}
@@ -1,4 +1,19 @@
+mod brush;
+mod census;
+mod classify;
+mod elementary;
+mod gif_export;
+mod heatmap;
+mod lenia;
+mod objects;
+mod pattern;
+mod rules;
+mod scenario;
+mod simulation;
+mod snapshot;
+mod url_fragment;
 mod utils;
+mod views;
 use core::fmt;
 use rand::Rng;
 
@@ -6,6 +21,21 @@ use wasm_bindgen::prelude::*;
 
 use web_sys::console::{self, log};
 
+pub use brush::{bresenham_line, Brush, BrushMode, BrushShape};
+pub use census::Census;
+pub use classify::ClassifiedObject;
+pub use elementary::Elementary;
+pub use gif_export::{GifError, GifOptions, MAX_GIF_GENERATIONS, MAX_GIF_PIXELS};
+pub use heatmap::MAX_ACTIVITY_WINDOW;
+pub use lenia::Lenia;
+pub use objects::{identify, Identity, Island, ObjectKind};
+pub use pattern::{Pattern, PatternError};
+pub use rules::{Automaton, Cyclic, Grid, LangtonsAnt, Life, Rule, RuleKind, Wireworld};
+pub use scenario::{Scenario, ScenarioError};
+pub use simulation::Simulation;
+pub use snapshot::SnapshotError;
+pub use url_fragment::FragmentError;
+
 //web_sysが提供するブラウザのコンソールにログを表示させるためのマクロをいじってprintln!風に書けるようにしている……らしい
 // A macro to provide `println!(..)`-style syntax for `console.log` logging.
 macro_rules! log {
@@ -40,67 +70,71 @@ impl Cell {
 }
 
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct Universe {
     width: u32,
     height: u32,
     cells: Vec<Cell>,
     next: Vec<Cell>,
     delta: Vec<Cell>,
+    //tickが呼ばれた回数 resetで0に戻る
+    generation: u64,
+    //cells,deltaの配列を作り直すたびに増える js側はこれが変わったら作っておいたビューを作り直す
+    version: u32,
+    //セルごとの直近の変化回数 enable_activityを呼んだときだけ記録する
+    activity: Option<heatmap::Activity>,
+    //誕生と生存の条件 普段はB3/S23
+    rule: Life,
 }
 
-impl Universe {
-    //列と行から線形になった配列の添字を取得する
-    fn get_index(&self, row: u32, column: u32) -> usize {
-        (row * self.width + column) as usize
-    }
-
-    //自身に隣接する生きているセルの数をカウントする
-    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
-        let mut count = 0;
-
-        //上下左右のセルを力技で定義する　ここで上下左右の端に対する例外処理を埋め込んでおく
-        let north = if row == 0 { self.height - 1 } else { row - 1 };
-
-        let south = if row == self.height - 1 { 0 } else { row + 1 };
-
-        let west = if column == 0 {
-            self.width - 1
-        } else {
-            column - 1
-        };
-
-        let east = if column == self.width - 1 {
-            0
-        } else {
-            column + 1
-        };
-
-        //8方向の隣接セルを力技で取得していく
-        let nw = self.get_index(north, west);
-        count += self.cells[nw] as u8;
+//列と行から線形になった配列の添字を取得する 行ごとに並べる Universe以外のグリッド(Leniaなど)も同じ並びにする
+pub(crate) fn get_index(width: u32, row: u32, column: u32) -> usize {
+    (row * width + column) as usize
+}
 
-        let n = self.get_index(north, column);
-        count += self.cells[n] as u8;
+//上下左右がつながったトーラスで、自身に隣接する8セルの添字 Universeとrules::Gridで共有する
+pub(crate) fn neighbor_indices(width: u32, height: u32, row: u32, column: u32) -> [usize; 8] {
+    //上下左右のセルを力技で定義する　ここで上下左右の端に対する例外処理を埋め込んでおく
+    let north = if row == 0 { height - 1 } else { row - 1 };
 
-        let ne = self.get_index(north, east);
-        count += self.cells[ne] as u8;
+    let south = if row == height - 1 { 0 } else { row + 1 };
 
-        let e = self.get_index(row, east);
-        count += self.cells[e] as u8;
+    let west = if column == 0 { width - 1 } else { column - 1 };
 
-        let se = self.get_index(south, east);
-        count += self.cells[se] as u8;
+    let east = if column == width - 1 { 0 } else { column + 1 };
 
-        let s = self.get_index(south, column);
-        count += self.cells[s] as u8;
+    //8方向の隣接セルを力技で取得していく
+    [
+        get_index(width, north, west),
+        get_index(width, north, column),
+        get_index(width, north, east),
+        get_index(width, row, east),
+        get_index(width, south, east),
+        get_index(width, south, column),
+        get_index(width, south, west),
+        get_index(width, row, west),
+    ]
+}
 
-        let sw = self.get_index(south, west);
-        count += self.cells[sw] as u8;
+impl Universe {
+    fn get_index(&self, row: u32, column: u32) -> usize {
+        get_index(self.width, row, column)
+    }
 
-        let w = self.get_index(row, west);
-        count += self.cells[w] as u8;
+    //自身に隣接する生きているセルの数をカウントする
+    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
+        neighbor_indices(self.width, self.height, row, column)
+            .iter()
+            .map(|&i| self.cells[i] as u8)
+            .sum()
+    }
 
-        count
+    pub fn rule(&self) -> Life {
+        self.rule
+    }
+    //セルはそのままで、次のtickからこのルールで進める
+    pub fn set_rule(&mut self, rule: Life) {
+        self.rule = rule;
     }
 
     pub fn get_cells(&self) -> &[Cell] {
@@ -116,9 +150,14 @@ impl Universe {
     }
 
     pub fn generate_init_cells(width: u32, height: u32) -> Vec<Cell> {
+        Universe::generate_cells_with_rng(width, height, &mut rand::thread_rng())
+    }
+
+    //乱数生成器を外から渡せる版 シードを固定したスープを作るときに使う
+    pub fn generate_cells_with_rng<R: Rng>(width: u32, height: u32, rng: &mut R) -> Vec<Cell> {
         let cells = (0..width * height)
             .map(|_i| {
-                if rand::thread_rng().gen_bool(0.5) {
+                if rng.gen_bool(0.5) {
                     Cell::Alive
                 } else {
                     Cell::Dead
@@ -134,7 +173,8 @@ impl Universe {
 #[wasm_bindgen]
 impl Universe {
     pub fn tick(&mut self) {
-        self.delta = self.delta.iter().map(|_i| Cell::Dead).collect();
+        //作り直すとjs側のビューが無効になるので、同じ配列をそのまま使い回す
+        self.delta.fill(Cell::Dead);
 
         for row in 0..self.height {
             for col in 0..self.width {
@@ -142,32 +182,18 @@ impl Universe {
                 let cell = self.cells[index];
                 let live_neighbors = self.live_neighbor_count(row, col);
 
-                let next_cell = match (cell, live_neighbors) {
-                    //セルが生きていて、隣接セルに生きているセルが1つ以下ならそのセルは人口不足により死ぬ
-                    (Cell::Alive, x) if x < 2 => {
-                        self.delta[index] = Cell::Alive;
-                        Cell::Dead
-                    }
-                    //セルが生きていて、隣接セルに生きているセルが2個か3個の場合はそのセルは行き続ける
-                    (Cell::Alive, 2) | (Cell::Alive, 3) => {
-                        self.delta[index] = Cell::Dead;
-                        Cell::Alive
-                    }
-                    //セルが生きていて、隣接セルに生きているセルが4つ以上ならそのセルは人口過多により死ぬ
-                    (Cell::Alive, x) if x > 3 => {
-                        self.delta[index] = Cell::Alive;
-                        Cell::Dead
-                    }
-                    //死んでいるセルは、隣接セルが3つの場合入植により生き返る
-                    (Cell::Dead, 3) => {
-                        self.delta[index] = Cell::Alive;
-                        Cell::Alive
-                    }
-                    //それ以外の場合は以前の状態を維持する(入力されたcellの状態を変数名otherwiseに束縛し、それをそのままcellに適用する)
-                    (otherwise, _) => {
-                        self.delta[index] = Cell::Dead;
-                        otherwise
-                    }
+                //生き残るか生まれるかはルール(普段はB3/S23)で決まる
+                //B3/S23なら、生きているセルは隣が2個か3個なら生き続け、それ以外は人口不足か過多で死ぬ 死んでいるセルは隣が3個なら生き返る
+                let next_cell = if self.rule.next_alive(cell == Cell::Alive, live_neighbors) {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                };
+                //deltaには状態が変わったセルだけAliveを入れる
+                self.delta[index] = if next_cell != cell {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
                 };
 
                 self.next[index] = next_cell;
@@ -175,6 +201,11 @@ impl Universe {
         }
 
         self.cells.swap_with_slice(&mut self.next);
+        self.generation += 1;
+
+        if let Some(activity) = &mut self.activity {
+            activity.record(&self.delta);
+        }
     }
 
     pub fn new() -> Universe {
@@ -195,12 +226,19 @@ impl Universe {
             cells,
             next,
             delta,
+            generation: 0,
+            version: 0,
+            activity: None,
+            rule: Life::default(),
         }
     }
 
     pub fn reset(&mut self) {
         self.cells = Universe::generate_init_cells(self.width, self.height);
         self.delta = vec![Cell::Dead; (self.width * self.height) as usize];
+        self.generation = 0;
+        self.version += 1;
+        self.clear_activity();
     }
 
     pub fn render(&self) -> String {
@@ -213,6 +251,9 @@ impl Universe {
     pub fn height(&self) -> u32 {
         self.height
     }
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
 
     //Cellの不変な(*staticな)ポインタを出力する
     //この場合の*staticは、ポインタそれ自体の不変性ではなく、ポインタが指し示す対象の不変性を保証する
@@ -233,6 +274,11 @@ impl Universe {
         //self.cellsを新しく設定する
         //0から新しいwidth * もともとのheightで得られる全セル数分のRangeを作って、それをすべてCell::Deadにした配列を作成する
         self.cells = (0..width * self.height).map(|_i| Cell::Dead).collect();
+        //tickで使う作業用の配列もセル数を合わせておかないとswap_with_sliceで落ちる
+        self.next = self.cells.clone();
+        self.delta = self.cells.clone();
+        self.version += 1;
+        self.clear_activity();
     }
     //heightを設定し、セルを全て初期化(Deadに)する
     pub fn set_height(&mut self, height: u32) {
@@ -241,6 +287,10 @@ impl Universe {
         //self.cellsを新しく設定する
         //0から新しいheight * もともとのwidthで得られる全セル数分のRangeを作って、それをすべてCell::Deadにした配列を作成する
         self.cells = (0..height * self.width).map(|_i| Cell::Dead).collect();
+        self.next = self.cells.clone();
+        self.delta = self.cells.clone();
+        self.version += 1;
+        self.clear_activity();
     }
 
     pub fn toggle_cell(&mut self, row: u32, column: u32) {
@@ -274,14 +324,19 @@ impl fmt::Display for Universe {
 pub struct Timer<'a> {
     name: &'a str,
 }
+//ネイティブ(cargo testやベンチ)ではブラウザのコンソールが無いので何もしない
 impl<'a> Timer<'a> {
     pub fn new(name: &'a str) -> Timer<'a> {
-        console::time_with_label(name);
+        if cfg!(target_arch = "wasm32") {
+            console::time_with_label(name);
+        }
         Timer { name }
     }
 }
 impl<'a> Drop for Timer<'a> {
     fn drop(&mut self) {
-        console::time_end_with_label(self.name);
+        if cfg!(target_arch = "wasm32") {
+            console::time_end_with_label(self.name);
+        }
     }
 }
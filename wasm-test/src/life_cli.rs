@@ -0,0 +1,147 @@
+//ブラウザなしでライフゲームを回すためのコマンドラインツール
+//パターンファイルを読み込んでN世代進め、統計や最終状態を標準出力に書き出す
+//
+//使い方: life-cli <パターンファイル> [--width W] [--height H] [--generations N]
+//                  [--rule B3/S23] [--output stats|rle|text] [--gif 出力ファイル] [--cell-size S]
+//盤面はUniverseと同じく上下左右がつながったトーラスだけ
+
+use std::{env, fs, process};
+
+use wasm_game_of_life::{GifOptions, Life, Pattern, Universe};
+
+enum Output {
+    Stats,
+    Rle,
+    Text,
+}
+
+struct Args {
+    path: String,
+    width: u32,
+    height: u32,
+    generations: u32,
+    rule: Life,
+    output: Output,
+    gif: Option<String>,
+    cell_size: u32,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: life-cli <pattern file> [--width W] [--height H] [--generations N] \
+         [--rule B3/S23] [--output stats|rle|text] [--gif FILE] [--cell-size S]"
+    );
+    process::exit(2);
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        path: String::new(),
+        width: 64,
+        height: 64,
+        generations: 100,
+        rule: Life::default(),
+        output: Output::Stats,
+        gif: None,
+        cell_size: 4,
+    };
+
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().unwrap_or_else(|| usage());
+        let number = |v: String| v.parse::<u32>().unwrap_or_else(|_| usage());
+
+        match arg.as_str() {
+            "--width" => args.width = number(value()),
+            "--height" => args.height = number(value()),
+            "--generations" => args.generations = number(value()),
+            "--rule" => args.rule = value().parse::<Life>().unwrap_or_else(|_| usage()),
+            //世代を進めてから間違いに気づかないように、ここで確かめておく
+            "--output" => {
+                args.output = match value().as_str() {
+                    "stats" => Output::Stats,
+                    "rle" => Output::Rle,
+                    "text" => Output::Text,
+                    _ => usage(),
+                }
+            }
+            "--gif" => args.gif = Some(value()),
+            "--cell-size" => args.cell_size = number(value()),
+            "-h" | "--help" => usage(),
+            _ if args.path.is_empty() && !arg.starts_with("--") => args.path = arg,
+            _ => usage(),
+        }
+    }
+
+    //セル数width * heightがu32を超えるとUniverseの配列の大きさを計算できないので、ほかの引数の誤りと同じく使い方を出す
+    if args.path.is_empty()
+        || args.width == 0
+        || args.height == 0
+        || args.width.checked_mul(args.height).is_none()
+    {
+        usage();
+    }
+    args
+}
+
+fn main() {
+    let args = parse_args();
+
+    let text = fs::read_to_string(&args.path).unwrap_or_else(|e| {
+        eprintln!("life-cli: {}: {}", args.path, e);
+        process::exit(1);
+    });
+    let pattern = Pattern::parse(&text).unwrap_or_else(|e| {
+        eprintln!("life-cli: {}: {}", args.path, e);
+        process::exit(1);
+    });
+
+    //パターンはUniverseの中央に置く
+    let mut universe = Universe::with_size(args.width, args.height);
+    universe.set_rule(args.rule);
+    universe.place_pattern(
+        &pattern,
+        args.height.saturating_sub(pattern.height) / 2,
+        args.width.saturating_sub(pattern.width) / 2,
+    );
+
+    //GIFは最初の状態から記録するので、進める前に書き出す
+    if let Some(path) = &args.gif {
+        let options = GifOptions {
+            generations: args.generations,
+            cell_size: args.cell_size,
+            ..GifOptions::default()
+        };
+        let bytes = universe.encode_gif(&options).unwrap_or_else(|e| {
+            eprintln!("life-cli: {}", e);
+            process::exit(1);
+        });
+        if let Err(e) = fs::write(path, bytes) {
+            eprintln!("life-cli: {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+
+    let initial_population = universe.population();
+    let mut min_population = initial_population;
+    let mut max_population = initial_population;
+    for _ in 0..args.generations {
+        universe.tick();
+        min_population = min_population.min(universe.population());
+        max_population = max_population.max(universe.population());
+    }
+
+    match args.output {
+        Output::Stats => {
+            println!("size: {}x{}", universe.width(), universe.height());
+            println!("rule: {}", universe.rule());
+            println!("generation: {}", universe.generation());
+            println!("initial population: {}", initial_population);
+            println!("final population: {}", universe.population());
+            println!("min population: {}", min_population);
+            println!("max population: {}", max_population);
+        }
+        Output::Rle => print!("{}", universe.rle()),
+        Output::Text => print!("{}", universe),
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+//盤面上の生きているセルをつながりごとの「物体」に分けて、それが何なのかを調べるための道具
+
+//周期を調べるときに何世代まで待つか これより長い周期の物体は未知扱いになる
+pub const MAX_PERIOD: u32 = 30;
+
+//つながった生きているセルのまとまり
+//row,columnは外接矩形の左上の位置(トーラス上の座標)で、cellsはそこからの相対位置
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Island {
+    pub row: u32,
+    pub column: u32,
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<(u32, u32)>,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Unknown,
+}
+
+//物体を単独で動かしてみて分かったこと
+//dx,dyは1周期で移動するセル数(右と下が正)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    pub kind: ObjectKind,
+    pub code: String,
+    pub period: u32,
+    pub dx: i32,
+    pub dy: i32,
+}
+
+impl Identity {
+    fn unknown() -> Identity {
+        Identity {
+            kind: ObjectKind::Unknown,
+            code: String::from("zz_UNKNOWN"),
+            period: 0,
+            dx: 0,
+            dy: 0,
+        }
+    }
+}
+
+//セルの並びを左上が(0,0)になるようにずらして並べ替える ずらした量も返す
+fn normalize(cells: &[(i32, i32)]) -> (i32, i32, Vec<(i32, i32)>) {
+    let min_r = cells.iter().map(|c| c.0).min().unwrap_or(0);
+    let min_c = cells.iter().map(|c| c.1).min().unwrap_or(0);
+    let mut normalized: Vec<(i32, i32)> =
+        cells.iter().map(|c| (c.0 - min_r, c.1 - min_c)).collect();
+    normalized.sort_unstable();
+    (min_r, min_c, normalized)
+}
+
+//apgsearchのapgcodeに倣った拡張Wechsler形式
+//5行ずつの帯に分けて、各列の5セルを1文字(0-9a-v)にする 0の連続はw(2個),x(3個),y?(4〜39個)に縮め、帯の区切りはz
+fn wechsler(cells: &[(i32, i32)]) -> String {
+    const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    let height = cells.iter().map(|c| c.0 + 1).max().unwrap_or(0);
+    let width = cells.iter().map(|c| c.1 + 1).max().unwrap_or(0);
+
+    let mut strips = vec![];
+    for strip in 0..(height + 4) / 5 {
+        let mut columns = vec![0u8; width as usize];
+        for &(r, c) in cells.iter().filter(|c| c.0 / 5 == strip) {
+            columns[c as usize] |= 1 << (r % 5);
+        }
+        while columns.last() == Some(&0) {
+            columns.pop();
+        }
+
+        let mut text = String::new();
+        let mut i = 0;
+        while i < columns.len() {
+            let zeros = columns[i..].iter().take_while(|&&v| v == 0).count().min(39);
+            match zeros {
+                0 => {
+                    text.push(DIGITS[columns[i] as usize] as char);
+                    i += 1;
+                    continue;
+                }
+                1 => text.push('0'),
+                2 => text.push('w'),
+                3 => text.push('x'),
+                n => {
+                    text.push('y');
+                    text.push(DIGITS[n - 4] as char);
+                }
+            }
+            i += zeros;
+        }
+        strips.push(text);
+    }
+    while strips.last().map(|s| s.is_empty()) == Some(true) {
+        strips.pop();
+    }
+
+    strips.join("z")
+}
+
+//全ての位相と8通りの向きの中で一番短く、同じ長さなら辞書順で一番小さいものを代表の表記にする
+fn canonical_code(phases: &[Vec<(i32, i32)>]) -> String {
+    let mut best: Option<String> = None;
+    for phase in phases {
+        //行と列の入れ替えの有無 × 上下反転 × 左右反転 の8通り
+        for orientation in 0..8 {
+            let moved: Vec<(i32, i32)> = phase
+                .iter()
+                .map(|&(r, c)| {
+                    let (r, c) = if orientation & 4 != 0 { (c, r) } else { (r, c) };
+                    let r = if orientation & 2 != 0 { -r } else { r };
+                    let c = if orientation & 1 != 0 { -c } else { c };
+                    (r, c)
+                })
+                .collect();
+            let code = wechsler(&normalize(&moved).2);
+            let better = match &best {
+                Some(b) => (code.len(), &code) < (b.len(), b),
+                None => true,
+            };
+            if better {
+                best = Some(code);
+            }
+        }
+    }
+    best.unwrap_or_default()
+}
+
+//物体を周りに何もない盤面に置いてMAX_PERIOD世代まで動かし、元の形に戻るまでの周期と移動量を調べる
+pub fn identify(cells: &[(u32, u32)]) -> Identity {
+    if cells.is_empty() {
+        return Identity::unknown();
+    }
+
+    let height = cells.iter().map(|c| c.0 + 1).max().unwrap_or(0);
+    let width = cells.iter().map(|c| c.1 + 1).max().unwrap_or(0);
+
+    //宇宙船は1周期でせいぜい周期の半分しか進まないので、その分の余白があれば端を回り込まない
+    let pad = MAX_PERIOD / 2 + 2;
+    let mut universe = Universe::with_size(width + pad * 2, height + pad * 2);
+    let shifted: Vec<(u32, u32)> = cells.iter().map(|c| (c.0 + pad, c.1 + pad)).collect();
+    universe.set_cells(&shifted);
+
+    let (start_r, start_c, start) = normalize(&universe.live_cells());
+    let mut phases = vec![start.clone()];
+
+    for generation in 1..=MAX_PERIOD {
+        universe.tick();
+        let (r, c, current) = normalize(&universe.live_cells());
+        if current.is_empty() {
+            return Identity::unknown();
+        }
+
+        if current == start {
+            let (dy, dx) = (r - start_r, c - start_c);
+            let (kind, prefix) = if dx != 0 || dy != 0 {
+                (ObjectKind::Spaceship, format!("xq{}", generation))
+            } else if generation == 1 {
+                (ObjectKind::StillLife, format!("xs{}", start.len()))
+            } else {
+                (ObjectKind::Oscillator, format!("xp{}", generation))
+            };
+
+            return Identity {
+                kind,
+                code: format!("{}_{}", prefix, canonical_code(&phases)),
+                period: generation,
+                dx,
+                dy,
+            };
+        }
+        phases.push(current);
+    }
+
+    Identity::unknown()
+}
+
+impl Universe {
+    //生きているセルの(行,列)の一覧
+    pub fn live_cells(&self) -> Vec<(i32, i32)> {
+        let mut cells = vec![];
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.cells[self.get_index(row, col)] == Cell::Alive {
+                    cells.push((row as i32, col as i32));
+                }
+            }
+        }
+        cells
+    }
+
+    //maskがtrueのセルを、チェビシェフ距離reach以内でつながっているものごとにまとめる
+    //盤面はトーラスなので、端をまたいだまとまりは座標を端の外まで伸ばしてから左上を求める
+    fn islands_in(&self, mask: &[bool], reach: i32) -> Vec<Island> {
+        let width = self.width as i32;
+        let height = self.height as i32;
+        let mut visited = vec![false; mask.len()];
+        let mut islands = vec![];
+
+        for start in 0..mask.len() {
+            if !mask[start] || visited[start] {
+                continue;
+            }
+            visited[start] = true;
+
+            let mut unwrapped = vec![];
+            let mut queue = VecDeque::new();
+            queue.push_back((start as i32 / width, start as i32 % width));
+
+            while let Some((r, c)) = queue.pop_front() {
+                unwrapped.push((r, c));
+                for dr in -reach..=reach {
+                    for dc in -reach..=reach {
+                        let (nr, nc) = (r + dr, c + dc);
+                        let idx = self
+                            .get_index(nr.rem_euclid(height) as u32, nc.rem_euclid(width) as u32);
+                        if mask[idx] && !visited[idx] {
+                            visited[idx] = true;
+                            queue.push_back((nr, nc));
+                        }
+                    }
+                }
+            }
+
+            let (min_r, min_c, cells) = normalize(&unwrapped);
+            islands.push(Island {
+                row: min_r.rem_euclid(height) as u32,
+                column: min_c.rem_euclid(width) as u32,
+                height: cells.iter().map(|c| c.0 as u32 + 1).max().unwrap_or(0),
+                width: cells.iter().map(|c| c.1 as u32 + 1).max().unwrap_or(0),
+                cells: cells.iter().map(|&(r, c)| (r as u32, c as u32)).collect(),
+            });
+        }
+
+        islands
+    }
+
+    //隣接8方向でつながった生きているセルのまとまり
+    pub fn islands(&self) -> Vec<Island> {
+        let mask: Vec<bool> = self.cells.iter().map(|&c| c == Cell::Alive).collect();
+        self.islands_in(&mask, 1)
+    }
+
+    //盤面を物体に分けてそれぞれを調べる
+    //ビーコンのように位相によって8方向ではつながらない物体もあるので、単独で周期が見つからなかったものは
+    //距離2以内にある同じく未知のまとまりと合わせてもう一度調べる
+    pub fn separate_objects(&self) -> Vec<(Island, Identity)> {
+        let mut objects = vec![];
+        let mut unresolved = vec![false; self.cells.len()];
+
+        for island in self.islands() {
+            let identity = identify(&island.cells);
+            if identity.kind == ObjectKind::Unknown {
+                for &(r, c) in island.cells.iter() {
+                    let idx = self.get_index(
+                        (island.row + r) % self.height,
+                        (island.column + c) % self.width,
+                    );
+                    unresolved[idx] = true;
+                }
+            } else {
+                objects.push((island, identity));
+            }
+        }
+
+        for island in self.islands_in(&unresolved, 2) {
+            let identity = identify(&island.cells);
+            objects.push((island, identity));
+        }
+
+        objects
+    }
+}
@@ -0,0 +1,266 @@
+use core::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Life, Universe};
+
+//ライフゲームでよく使われるパターンファイル(RLE形式と.cellsのプレーンテキスト形式)の読み書き
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PatternError {
+    InvalidHeader(String),
+    InvalidCharacter(char),
+    //ヘッダのx,yより外側にセルがある
+    OutOfBounds,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::InvalidHeader(line) => write!(f, "pattern: invalid header {:?}", line),
+            PatternError::InvalidCharacter(c) => write!(f, "pattern: invalid character {:?}", c),
+            PatternError::OutOfBounds => write!(f, "pattern: cell outside of the pattern size"),
+        }
+    }
+}
+
+//読み込んだパターン 生きているセルの(行,列)をUniverse::set_cellsと同じ並びで持つ
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<(u32, u32)>,
+}
+
+impl Pattern {
+    //"x = "で始まるヘッダ行があればRLE、なければプレーンテキストとして読む
+    pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+        let is_rle = text
+            .lines()
+            .map(|line| line.trim_start())
+            .any(|line| line.starts_with("x ") || line.starts_with("x="));
+
+        if is_rle {
+            Pattern::parse_rle(text)
+        } else {
+            Pattern::parse_plaintext(text)
+        }
+    }
+
+    //RLE形式 例: "x = 3, y = 3\nbo$2bo$3o!"
+    //bが死んでいるセル、oが生きているセル、$が改行、!が終端で、前に付いた数字は繰り返し回数
+    pub fn parse_rle(text: &str) -> Result<Pattern, PatternError> {
+        let mut lines = text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let header = lines.next().unwrap_or("");
+        let mut width = None;
+        let mut height = None;
+        for item in header.split(',') {
+            let mut kv = item.splitn(2, '=').map(|s| s.trim());
+            let value = |v: Option<&str>| {
+                v.and_then(|v| v.parse::<u32>().ok())
+                    .ok_or_else(|| PatternError::InvalidHeader(header.to_string()))
+            };
+            match kv.next() {
+                Some("x") => width = Some(value(kv.next())?),
+                Some("y") => height = Some(value(kv.next())?),
+                //ruleなどそれ以外の項目は今は読み飛ばす
+                _ => {}
+            }
+        }
+        let (width, height) = match (width, height) {
+            (Some(w), Some(h)) => (w, h),
+            _ => return Err(PatternError::InvalidHeader(header.to_string())),
+        };
+
+        let mut cells = vec![];
+        let mut row = 0u32;
+        let mut col = 0u32;
+        let mut count = 0u32;
+
+        'body: for line in lines {
+            for c in line.chars() {
+                match c {
+                    '0'..='9' => {
+                        count = count
+                            .saturating_mul(10)
+                            .saturating_add(c as u32 - '0' as u32);
+                        continue;
+                    }
+                    //桁の大きな繰り返し回数でもあふれないように、あふれたらはみ出しとして扱う
+                    'b' | '.' => {
+                        col = col
+                            .checked_add(count.max(1))
+                            .ok_or(PatternError::OutOfBounds)?
+                    }
+                    'o' | 'A'..='Z' => {
+                        for _ in 0..count.max(1) {
+                            if row >= height || col >= width {
+                                return Err(PatternError::OutOfBounds);
+                            }
+                            cells.push((row, col));
+                            col += 1;
+                        }
+                    }
+                    '$' => {
+                        row = row
+                            .checked_add(count.max(1))
+                            .ok_or(PatternError::OutOfBounds)?;
+                        col = 0;
+                    }
+                    '!' => break 'body,
+                    c if c.is_whitespace() => {}
+                    c => return Err(PatternError::InvalidCharacter(c)),
+                }
+                count = 0;
+            }
+        }
+
+        Ok(Pattern {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    //プレーンテキスト形式 '.'が死んでいるセル、'O'(か'*')が生きているセル、'!'で始まる行はコメント
+    pub fn parse_plaintext(text: &str) -> Result<Pattern, PatternError> {
+        let mut cells = vec![];
+        let mut width = 0;
+        let mut height = 0;
+
+        for line in text.lines().filter(|line| !line.starts_with('!')) {
+            let line = line.trim_end();
+            for (col, c) in line.chars().enumerate() {
+                match c {
+                    '.' => {}
+                    'O' | 'o' | '*' => cells.push((height, col as u32)),
+                    c => return Err(PatternError::InvalidCharacter(c)),
+                }
+            }
+            width = width.max(line.chars().count() as u32);
+            height += 1;
+        }
+
+        Ok(Pattern {
+            width,
+            height,
+            cells,
+        })
+    }
+}
+
+impl Universe {
+    //指定した大きさの、全てのセルが死んでいるUniverse
+    pub fn with_size(width: u32, height: u32) -> Universe {
+        let cells = vec![Cell::Dead; (width * height) as usize];
+
+        Universe {
+            width,
+            height,
+            next: cells.clone(),
+            delta: cells.clone(),
+            cells,
+            generation: 0,
+            version: 0,
+            activity: None,
+            rule: Life::default(),
+        }
+    }
+
+    //パターンの左上を(row,column)に合わせて配置する はみ出した分は反対側に回り込む
+    //jsから大きな座標を渡されてもあふれないようにu64で足してから回り込ませる セルが無いUniverseには何も置かない
+    pub fn place_pattern(&mut self, pattern: &Pattern, row: u32, column: u32) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let wrap = |start: u32, offset: u32, len: u32| ((start as u64 + offset as u64) % len as u64) as u32;
+        for &(r, c) in pattern.cells.iter() {
+            let idx = self.get_index(wrap(row, r, self.height), wrap(column, c, self.width));
+            self.cells[idx] = Cell::Alive;
+        }
+    }
+
+    //生きているセルを囲む最小の範囲をRLE形式で書き出す
+    pub fn rle(&self) -> String {
+        let alive = |row: u32, col: u32| self.cells[self.get_index(row, col)] == Cell::Alive;
+
+        let rows = (0..self.height).filter(|&r| (0..self.width).any(|c| alive(r, c)));
+        let cols = (0..self.width).filter(|&c| (0..self.height).any(|r| alive(r, c)));
+        let (top, bottom) = (rows.clone().min(), rows.max());
+        let (left, right) = (cols.clone().min(), cols.max());
+
+        let (top, bottom, left, right) = match (top, bottom, left, right) {
+            (Some(t), Some(b), Some(l), Some(r)) => (t, b, l, r),
+            _ => return format!("x = 0, y = 0, rule = {}\n!\n", self.rule),
+        };
+
+        //(繰り返し回数,記号)の並びを作ってから70文字ごとに折り返して書き出す
+        let mut runs: Vec<(u32, char)> = vec![];
+        let push = |runs: &mut Vec<(u32, char)>, c: char| match runs.last_mut() {
+            Some((n, last)) if *last == c => *n += 1,
+            _ => runs.push((1, c)),
+        };
+        for row in top..=bottom {
+            if row > top {
+                push(&mut runs, '$');
+            }
+            //行末の死んでいるセルは省略できる
+            let last = (left..=right).rev().find(|&c| alive(row, c));
+            if let Some(last) = last {
+                for col in left..=last {
+                    push(&mut runs, if alive(row, col) { 'o' } else { 'b' });
+                }
+            }
+        }
+        runs.push((1, '!'));
+
+        let mut out = format!(
+            "x = {}, y = {}, rule = {}\n",
+            right - left + 1,
+            bottom - top + 1,
+            self.rule
+        );
+        let mut line_len = 0;
+        for (n, c) in runs {
+            let item = if n > 1 {
+                format!("{}{}", n, c)
+            } else {
+                c.to_string()
+            };
+            if line_len + item.len() > 70 {
+                out.push('\n');
+                line_len = 0;
+            }
+            line_len += item.len();
+            out.push_str(&item);
+        }
+        out.push('\n');
+
+        out
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    pub fn population(&self) -> u32 {
+        self.cells
+            .iter()
+            .filter(|&&cell| cell == Cell::Alive)
+            .count() as u32
+    }
+
+    pub fn to_rle(&self) -> String {
+        self.rle()
+    }
+
+    //RLEかプレーンテキストのパターンを(row,column)に配置する
+    pub fn load_pattern(&mut self, text: &str, row: u32, column: u32) -> Result<(), JsValue> {
+        let pattern = Pattern::parse(text).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.place_pattern(&pattern, row, column);
+        Ok(())
+    }
+}
@@ -0,0 +1,443 @@
+use core::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+//Dead/Alive以外の状態を持つセルオートマトンを同じ盤面の仕組みで動かすためのルールの差し替え口
+//セルの状態は0から始まるu8で、何状態あるか、各状態を何色で描くか、次の世代をどう決めるかをルール側が持つ
+
+//状態をu8で持つトーラス状の盤面
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<u8>,
+}
+
+impl Grid {
+    pub fn new(width: u32, height: u32) -> Grid {
+        Grid {
+            width,
+            height,
+            cells: vec![0; (width * height) as usize],
+        }
+    }
+
+    //列と行から線形になった配列の添字を取得する 並びはUniverseと同じ
+    pub fn get_index(&self, row: u32, column: u32) -> usize {
+        crate::get_index(self.width, row, column)
+    }
+
+    //盤面の外を指したときは反対側に回り込む
+    pub fn get(&self, row: i32, column: i32) -> u8 {
+        let r = row.rem_euclid(self.height as i32) as u32;
+        let c = column.rem_euclid(self.width as i32) as u32;
+        self.cells[self.get_index(r, c)]
+    }
+
+    //隣接8セルのうち状態がstateのものの数
+    //隣の数え方はUniverseと共通
+    pub fn count_neighbors(&self, row: u32, column: u32, state: u8) -> u8 {
+        crate::neighbor_indices(self.width, self.height, row, column)
+            .iter()
+            .filter(|&&i| self.cells[i] == state)
+            .count() as u8
+    }
+}
+
+pub trait Rule {
+    //状態の数 toggle_cellはこの数で一周する
+    fn states(&self) -> u8;
+
+    //各状態の色(0xRRGGBB) 長さはstates()と同じ
+    fn palette(&self) -> Vec<u32>;
+
+    //1セルの次の状態 普通のセルオートマトンはこれだけ書けば良い
+    fn next_state(&self, grid: &Grid, row: u32, column: u32) -> u8;
+
+    //盤面全体を1世代進める 蟻のように盤面の外に状態を持つルールはこちらを上書きする
+    fn step(&mut self, grid: &Grid, next: &mut Grid) {
+        for row in 0..grid.height {
+            for col in 0..grid.width {
+                let idx = grid.get_index(row, col);
+                next.cells[idx] = self.next_state(grid, row, col);
+            }
+        }
+    }
+
+    //セルの上に重ねて描くもの (行,列,色)
+    fn overlay(&self) -> Vec<(u32, u32, u32)> {
+        vec![]
+    }
+}
+
+//ライフゲーム系のルール(B3/S23のような表記) 0:死 1:生
+//birth,survivalは隣の生きているセルがn個のときに生まれる/生き残るならビットnが立っている
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Life {
+    pub birth: u16,
+    pub survival: u16,
+}
+
+//いつものライフゲーム(B3/S23)
+impl Default for Life {
+    fn default() -> Life {
+        Life {
+            birth: 1 << 3,
+            survival: 1 << 2 | 1 << 3,
+        }
+    }
+}
+
+impl Life {
+    //"B36/S23"のような表記から 大文字小文字とBとSの順番は問わない
+    fn parse_notation(text: &str) -> Option<Life> {
+        let mut birth = None;
+        let mut survival = None;
+        for part in text.trim().split('/') {
+            let mut chars = part.chars();
+            let target = match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => &mut birth,
+                Some('S') => &mut survival,
+                _ => return None,
+            };
+            let mut mask = 0u16;
+            for c in chars {
+                match c.to_digit(10) {
+                    Some(n) if n <= 8 => mask |= 1 << n,
+                    _ => return None,
+                }
+            }
+            if target.replace(mask).is_some() {
+                return None;
+            }
+        }
+        Some(Life {
+            birth: birth?,
+            survival: survival?,
+        })
+    }
+
+    //今生きているかと、隣の生きているセルの数から、次の世代で生きているか
+    pub fn next_alive(&self, alive: bool, neighbors: u8) -> bool {
+        let mask = if alive { self.survival } else { self.birth };
+        mask & 1 << neighbors != 0
+    }
+}
+
+//"B36/S23".parse::<Life>()のように使う 書けない表記はそのままエラーの文に入れる
+impl FromStr for Life {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Life, String> {
+        Life::parse_notation(text)
+            .ok_or_else(|| format!("rule: expected B/S notation like B3/S23, got {:?}", text))
+    }
+}
+
+impl fmt::Display for Life {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = |mask: u16| -> String {
+            (0..=8)
+                .filter(|n| mask & 1 << n != 0)
+                .map(|n: u32| std::char::from_digit(n, 10).unwrap())
+                .collect()
+        };
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survival))
+    }
+}
+
+impl Rule for Life {
+    fn states(&self) -> u8 {
+        2
+    }
+    fn palette(&self) -> Vec<u32> {
+        vec![0xffffff, 0x000000]
+    }
+    fn next_state(&self, grid: &Grid, row: u32, column: u32) -> u8 {
+        let alive = grid.cells[grid.get_index(row, column)] == 1;
+        self.next_alive(alive, grid.count_neighbors(row, column, 1)) as u8
+    }
+}
+
+//Wireworld 0:空白 1:電子の頭 2:電子の尾 3:導線
+//頭は尾に、尾は導線に戻り、導線は隣に頭が1つか2つあるときだけ頭になる
+pub struct Wireworld;
+
+impl Rule for Wireworld {
+    fn states(&self) -> u8 {
+        4
+    }
+    fn palette(&self) -> Vec<u32> {
+        vec![0x000000, 0x2060ff, 0xff4020, 0xffd000]
+    }
+    fn next_state(&self, grid: &Grid, row: u32, column: u32) -> u8 {
+        match grid.cells[grid.get_index(row, column)] {
+            1 => 2,
+            2 => 3,
+            3 => match grid.count_neighbors(row, column, 1) {
+                1 | 2 => 1,
+                _ => 3,
+            },
+            _ => 0,
+        }
+    }
+}
+
+//巡回セルオートマトン 状態kのセルは、隣にk+1(mod states)のセルがthreshold個以上あるとk+1になる
+pub struct Cyclic {
+    states: u8,
+    threshold: u8,
+}
+
+impl Cyclic {
+    //状態が1つ以下だと巡回できず、statesで割る所が0除算になるので最低2にする
+    pub fn new(states: u8, threshold: u8) -> Cyclic {
+        Cyclic {
+            states: states.max(2),
+            threshold,
+        }
+    }
+}
+
+impl Rule for Cyclic {
+    fn states(&self) -> u8 {
+        self.states
+    }
+    //状態ごとに色相を一周させる
+    fn palette(&self) -> Vec<u32> {
+        (0..self.states)
+            .map(|i| {
+                let h = i as f64 / self.states as f64 * 6.0;
+                let x = (255.0 * (1.0 - (h % 2.0 - 1.0).abs())) as u32;
+                match h as u32 {
+                    0 => 0xff0000 | x << 8,
+                    1 => x << 16 | 0x00ff00,
+                    2 => 0x00ff00 | x,
+                    3 => x << 8 | 0x0000ff,
+                    4 => x << 16 | 0x0000ff,
+                    _ => 0xff0000 | x,
+                }
+            })
+            .collect()
+    }
+    fn next_state(&self, grid: &Grid, row: u32, column: u32) -> u8 {
+        let state = grid.cells[grid.get_index(row, column)];
+        let successor = (state + 1) % self.states;
+        if grid.count_neighbors(row, column, successor) >= self.threshold {
+            successor
+        } else {
+            state
+        }
+    }
+}
+
+//ラングトンの蟻 0:白 1:黒
+//蟻は白のマスでは右に、黒のマスでは左に向きを変え、そのマスの色を反転して1マス進む
+pub struct LangtonsAnt {
+    pub row: u32,
+    pub column: u32,
+    //0:北 1:東 2:南 3:西
+    pub direction: u8,
+}
+
+impl Rule for LangtonsAnt {
+    fn states(&self) -> u8 {
+        2
+    }
+    fn palette(&self) -> Vec<u32> {
+        vec![0xffffff, 0x000000]
+    }
+    //蟻のいないマスは変わらない
+    fn next_state(&self, grid: &Grid, row: u32, column: u32) -> u8 {
+        grid.cells[grid.get_index(row, column)]
+    }
+    fn step(&mut self, grid: &Grid, next: &mut Grid) {
+        next.cells.copy_from_slice(&grid.cells);
+
+        let idx = grid.get_index(self.row, self.column);
+        self.direction = if grid.cells[idx] == 0 {
+            (self.direction + 1) % 4
+        } else {
+            (self.direction + 3) % 4
+        };
+        next.cells[idx] = 1 - grid.cells[idx];
+
+        let (dr, dc) = match self.direction {
+            0 => (-1, 0),
+            1 => (0, 1),
+            2 => (1, 0),
+            _ => (0, -1),
+        };
+        self.row = (self.row as i32 + dr).rem_euclid(grid.height as i32) as u32;
+        self.column = (self.column as i32 + dc).rem_euclid(grid.width as i32) as u32;
+    }
+    fn overlay(&self) -> Vec<(u32, u32, u32)> {
+        vec![(self.row, self.column, 0xff2020)]
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleKind {
+    Life,
+    Wireworld,
+    LangtonsAnt,
+    Cyclic,
+}
+
+//ルールを差し替えられる多状態版のUniverse
+#[wasm_bindgen]
+pub struct Automaton {
+    kind: RuleKind,
+    grid: Grid,
+    next: Grid,
+    rule: Box<dyn Rule>,
+    generation: u64,
+}
+
+impl Automaton {
+    fn make_rule(kind: RuleKind, width: u32, height: u32) -> Box<dyn Rule> {
+        match kind {
+            RuleKind::Life => Box::new(Life::default()),
+            RuleKind::Wireworld => Box::new(Wireworld),
+            RuleKind::LangtonsAnt => Box::new(LangtonsAnt {
+                row: height / 2,
+                column: width / 2,
+                direction: 0,
+            }),
+            RuleKind::Cyclic => Box::new(Cyclic::new(12, 1)),
+        }
+    }
+
+    //好きなルールで作る RuleKindにないルールを試すとき用 resetするとkindの標準のルールに戻る
+    //set_stateやtoggle_cellはstates()で割り、render_rgbaは状態の番号でパレットを引くので、
+    //状態が2つ未満のルールや、パレットの色が状態の数より少ないルールはエラーにする
+    pub fn with_rule(
+        rule: Box<dyn Rule>,
+        kind: RuleKind,
+        width: u32,
+        height: u32,
+    ) -> Result<Automaton, String> {
+        let states = rule.states();
+        if states < 2 {
+            return Err(format!(
+                "automaton: a rule needs at least 2 states, got {}",
+                states
+            ));
+        }
+        let colors = rule.palette().len();
+        if colors < states as usize {
+            return Err(format!(
+                "automaton: palette has {} colors for {} states",
+                colors, states
+            ));
+        }
+
+        Ok(Automaton {
+            kind,
+            grid: Grid::new(width, height),
+            next: Grid::new(width, height),
+            rule,
+            generation: 0,
+        })
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+}
+
+#[wasm_bindgen]
+impl Automaton {
+    pub fn new(kind: RuleKind, width: u32, height: u32) -> Automaton {
+        crate::utils::set_panic_hook();
+
+        //RuleKindの標準のルールはどれも状態とパレットがそろっているので失敗しない
+        Automaton::with_rule(
+            Automaton::make_rule(kind, width, height),
+            kind,
+            width,
+            height,
+        )
+        .unwrap()
+    }
+
+    pub fn tick(&mut self) {
+        self.rule.step(&self.grid, &mut self.next);
+        std::mem::swap(&mut self.grid, &mut self.next);
+        self.generation += 1;
+    }
+
+    //全セルを0に戻し、ルールの状態(蟻の位置など)も作り直す
+    pub fn reset(&mut self) {
+        let (width, height) = (self.grid.width, self.grid.height);
+        self.rule = Automaton::make_rule(self.kind, width, height);
+        self.grid = Grid::new(width, height);
+        self.generation = 0;
+    }
+
+    //全セルをランダムな状態にする
+    pub fn randomize(&mut self) {
+        let states = self.rule.states();
+        let mut rng = rand::thread_rng();
+        for cell in self.grid.cells.iter_mut() {
+            *cell = rng.gen_range(0..states);
+        }
+    }
+
+    pub fn kind(&self) -> RuleKind {
+        self.kind
+    }
+    pub fn states(&self) -> u8 {
+        self.rule.states()
+    }
+    pub fn palette(&self) -> Vec<u32> {
+        self.rule.palette()
+    }
+    pub fn width(&self) -> u32 {
+        self.grid.width
+    }
+    pub fn height(&self) -> u32 {
+        self.grid.height
+    }
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    //状態(u8)の配列の先頭ポインタ
+    pub fn cells(&self) -> *const u8 {
+        self.grid.cells.as_ptr()
+    }
+
+    pub fn get_state(&self, row: u32, column: u32) -> u8 {
+        self.grid.cells[self.grid.get_index(row, column)]
+    }
+    pub fn set_state(&mut self, row: u32, column: u32, state: u8) {
+        let idx = self.grid.get_index(row, column);
+        self.grid.cells[idx] = state % self.rule.states();
+    }
+
+    //Universe::toggle_cellと同じくクリックで使う 状態を1つずつ進めて一周させる
+    pub fn toggle_cell(&mut self, row: u32, column: u32) {
+        let state = self.get_state(row, column);
+        self.set_state(row, column, state + 1);
+    }
+
+    //パレットで色を付けたcanvasのImageData用RGBA配列(1セル1ピクセル)
+    pub fn render_rgba(&self) -> Vec<u8> {
+        let palette = self.rule.palette();
+        let mut rgba = Vec::with_capacity(self.grid.cells.len() * 4);
+        for &state in self.grid.cells.iter() {
+            let color = palette[state as usize];
+            rgba.extend_from_slice(&[(color >> 16) as u8, (color >> 8) as u8, color as u8, 255]);
+        }
+
+        for (row, col, color) in self.rule.overlay() {
+            let i = self.grid.get_index(row, col) * 4;
+            rgba[i..i + 3].copy_from_slice(&[(color >> 16) as u8, (color >> 8) as u8, color as u8]);
+        }
+        rgba
+    }
+}
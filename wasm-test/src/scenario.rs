@@ -0,0 +1,252 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+
+use crate::{Cell, Life, Pattern, PatternError, Universe};
+
+//Universeの回帰テストをRustを書かずに書けるようにする小さなスクリプト
+//1行1命令で、#から行末まではコメント
+//
+//  size 64 64                   盤面を全セル死んだ状態で作り直す(最初に書かなければ64x64) ルールはそのまま
+//  rule B36/S23                 次のrunからこのルール(B/S表記)で進める(最初に書かなければB3/S23)
+//  pattern glider               次のendまでの行をRLEかプレーンテキストとしてgliderという名前で登録する
+//  .O.
+//  ..O
+//  OOO
+//  end
+//  place glider at 10 10        パターンの左上を(行,列)に合わせて置く はみ出した分は反対側に回り込む
+//  run 4                        4世代進める
+//  assert population 5          生きているセルの数
+//  assert alive 11 12           (行,列)のセルが生きている
+//  assert dead 0 0              (行,列)のセルが死んでいる
+//  assert pattern glider at 11 11   パターンの外接矩形の中がパターンと完全に一致する
+
+const DEFAULT_SIZE: u32 = 64;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScenarioError {
+    //書き方が間違っている (行番号,説明)
+    Syntax(usize, String),
+    UnknownPattern(usize, String),
+    Pattern(usize, PatternError),
+    //assertが成り立たなかった (行番号,説明)
+    Assertion(usize, String),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScenarioError::Syntax(line, message) => {
+                write!(f, "scenario: line {}: {}", line, message)
+            }
+            ScenarioError::UnknownPattern(line, name) => {
+                write!(f, "scenario: line {}: unknown pattern {:?}", line, name)
+            }
+            ScenarioError::Pattern(line, e) => write!(f, "scenario: line {}: {}", line, e),
+            ScenarioError::Assertion(line, message) => {
+                write!(f, "scenario: line {}: assertion failed: {}", line, message)
+            }
+        }
+    }
+}
+
+pub struct Scenario {
+    universe: Universe,
+    patterns: HashMap<String, Pattern>,
+    //通ったassertの数
+    assertions: usize,
+}
+
+impl Scenario {
+    pub fn new() -> Scenario {
+        Scenario {
+            universe: Universe::with_size(DEFAULT_SIZE, DEFAULT_SIZE),
+            patterns: HashMap::new(),
+            assertions: 0,
+        }
+    }
+
+    pub fn universe(&self) -> &Universe {
+        &self.universe
+    }
+    pub fn assertions(&self) -> usize {
+        self.assertions
+    }
+
+    //スクリプトを上から順に実行して、最初に失敗したところでエラーを返す
+    pub fn run(&mut self, script: &str) -> Result<(), ScenarioError> {
+        let mut lines = script.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        while let Some((number, line)) = lines.next() {
+            let words: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+
+            match words.as_slice() {
+                [] => {}
+                ["size", width, height] => {
+                    let width = parse_number(number, width)?;
+                    let height = parse_number(number, height)?;
+                    if width == 0 || height == 0 {
+                        return Err(syntax(number, "size must not be zero"));
+                    }
+                    let rule = self.universe.rule();
+                    self.universe = Universe::with_size(width, height);
+                    self.universe.set_rule(rule);
+                }
+                ["rule", notation] => {
+                    let rule = notation.parse::<Life>().map_err(|e| syntax(number, &e))?;
+                    self.universe.set_rule(rule);
+                }
+                ["pattern", name] => {
+                    //パターンの本体はRLEの#Nなどを含むことがあるので、コメントを取り除かずにそのまま渡す
+                    let mut text = String::new();
+                    loop {
+                        match lines.next() {
+                            Some((_, body)) if body.trim() == "end" => break,
+                            Some((_, body)) => {
+                                text.push_str(body);
+                                text.push('\n');
+                            }
+                            None => return Err(syntax(number, "pattern without end")),
+                        }
+                    }
+                    let pattern =
+                        Pattern::parse(&text).map_err(|e| ScenarioError::Pattern(number, e))?;
+                    self.patterns.insert(name.to_string(), pattern);
+                }
+                ["place", name, "at", row, column] => {
+                    let (row, column) = self.position(number, row, column)?;
+                    let pattern = self.pattern(number, name)?.clone();
+                    self.universe.place_pattern(&pattern, row, column);
+                }
+                ["run", generations] => {
+                    for _ in 0..parse_number(number, generations)? {
+                        self.universe.tick();
+                    }
+                }
+                ["assert", "population", expected] => {
+                    let expected = parse_number(number, expected)?;
+                    let actual = self.universe.population();
+                    self.check(number, actual == expected, || {
+                        format!("population is {}, expected {}", actual, expected)
+                    })?;
+                }
+                ["assert", state @ ("alive" | "dead"), row, column] => {
+                    let (row, column) = self.position(number, row, column)?;
+                    let expected = if *state == "alive" {
+                        Cell::Alive
+                    } else {
+                        Cell::Dead
+                    };
+                    let actual = self.cell(row, column);
+                    self.check(number, actual == expected, || {
+                        format!("cell ({}, {}) is {:?}", row, column, actual)
+                    })?;
+                }
+                ["assert", "pattern", name, "at", row, column] => {
+                    let (row, column) = self.position(number, row, column)?;
+                    let mismatch = self.pattern_mismatch(number, name, row, column)?;
+                    self.check(number, mismatch.is_none(), || {
+                        let (r, c) = mismatch.unwrap();
+                        format!(
+                            "pattern {} does not match at ({}, {}): cell ({}, {}) differs",
+                            name, row, column, r, c
+                        )
+                    })?;
+                }
+                _ => {
+                    return Err(syntax(
+                        number,
+                        &format!("unknown command {:?}", line.trim()),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn pattern(&self, number: usize, name: &str) -> Result<&Pattern, ScenarioError> {
+        self.patterns
+            .get(name)
+            .ok_or_else(|| ScenarioError::UnknownPattern(number, name.to_string()))
+    }
+
+    //盤面の外を指していたらエラーにする(placeと違って回り込ませない)
+    fn position(
+        &self,
+        number: usize,
+        row: &str,
+        column: &str,
+    ) -> Result<(u32, u32), ScenarioError> {
+        let row = parse_number(number, row)?;
+        let column = parse_number(number, column)?;
+        if row >= self.universe.height() || column >= self.universe.width() {
+            return Err(syntax(
+                number,
+                &format!("({}, {}) is outside of the universe", row, column),
+            ));
+        }
+        Ok((row, column))
+    }
+
+    fn cell(&self, row: u32, column: u32) -> Cell {
+        let row = row % self.universe.height();
+        let column = column % self.universe.width();
+        self.universe.get_cells()[self.universe.get_index(row, column)]
+    }
+
+    //パターンの外接矩形を(row,column)に合わせたとき、最初に食い違ったセルの盤面上の位置
+    fn pattern_mismatch(
+        &self,
+        number: usize,
+        name: &str,
+        row: u32,
+        column: u32,
+    ) -> Result<Option<(u32, u32)>, ScenarioError> {
+        let pattern = self.pattern(number, name)?;
+        let alive: HashSet<&(u32, u32)> = pattern.cells.iter().collect();
+
+        for r in 0..pattern.height {
+            for c in 0..pattern.width {
+                let expected = if alive.contains(&(r, c)) {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                };
+                if self.cell(row + r, column + c) != expected {
+                    return Ok(Some((
+                        (row + r) % self.universe.height(),
+                        (column + c) % self.universe.width(),
+                    )));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn check(
+        &mut self,
+        number: usize,
+        passed: bool,
+        message: impl FnOnce() -> String,
+    ) -> Result<(), ScenarioError> {
+        if !passed {
+            return Err(ScenarioError::Assertion(number, message()));
+        }
+        self.assertions += 1;
+        Ok(())
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Scenario {
+        Scenario::new()
+    }
+}
+
+fn syntax(number: usize, message: &str) -> ScenarioError {
+    ScenarioError::Syntax(number, message.to_string())
+}
+
+fn parse_number(number: usize, word: &str) -> Result<u32, ScenarioError> {
+    word.parse()
+        .map_err(|_| syntax(number, &format!("expected a number, got {:?}", word)))
+}
@@ -0,0 +1,303 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::{Brush, Cell, Universe};
+
+//再生・一時停止と描画ループをRust側で持つためのコントローラー
+//wasm-bouncing-ballsのmain_loopと同じく、requestAnimationFrameのクロージャをRc<RefCell<..>>で自分自身に渡して回し続ける
+//速度は「1秒あたりの世代数」で指定し、フレーム間の経過時間から何回tickするかを決める
+
+struct Controller {
+    universe: Universe,
+    //1秒あたりに進める世代数
+    speed: f64,
+    //まだ進めていない端数の世代
+    accumulator: f64,
+    //1フレームで進める世代数の上限 重いときにフレームが詰まり続けないようにする
+    max_ticks_per_frame: u32,
+    //1フレームでtickに使って良い時間(ミリ秒)
+    frame_budget_ms: f64,
+    //run_untilで指定された世代 到達したら一時停止する
+    target_generation: Option<u64>,
+    playing: bool,
+    //最後にon_frameを呼んだときの再生状態 変わっていたらtickしていなくても呼ぶ
+    notified_playing: bool,
+    last_timestamp: Option<f64>,
+    //フレームの始めのセルと、そこから変化したセル(Aliveが変化あり)
+    //1フレームで何回tickしてもjs側は差分描画できる
+    frame_start: Vec<Cell>,
+    frame_delta: Vec<Cell>,
+    on_frame: Option<js_sys::Function>,
+}
+
+impl Controller {
+    fn begin_frame(&mut self) {
+        self.frame_start.clear();
+        self.frame_start
+            .extend_from_slice(self.universe.get_cells());
+    }
+
+    fn end_frame(&mut self) {
+        let cells = self.universe.get_cells();
+        self.frame_delta.clear();
+        self.frame_delta
+            .extend(self.frame_start.iter().zip(cells.iter()).map(|(a, b)| {
+                if a != b {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            }));
+    }
+
+    //n世代進める run_untilの目標に届いたらそこで止めて一時停止する
+    fn run_ticks(&mut self, n: u32, deadline: &dyn Fn() -> bool) -> u32 {
+        let mut ticks = 0;
+        while ticks < n {
+            if let Some(target) = self.target_generation {
+                if self.universe.generation() >= target {
+                    self.target_generation = None;
+                    self.playing = false;
+                    self.accumulator = 0.0;
+                    break;
+                }
+            }
+            if ticks > 0 && deadline() {
+                break;
+            }
+            self.universe.tick();
+            ticks += 1;
+        }
+        ticks
+    }
+
+    //前のフレームからelapsed_msミリ秒経ったとして、速度に応じた回数だけtickする
+    fn advance(&mut self, elapsed_ms: f64, deadline: &dyn Fn() -> bool) -> u32 {
+        if !self.playing {
+            return 0;
+        }
+
+        self.accumulator += elapsed_ms.max(0.0) * self.speed / 1000.0;
+        let wanted = (self.accumulator.floor() as u32).min(self.max_ticks_per_frame);
+
+        self.begin_frame();
+        let ticks = self.run_ticks(wanted, deadline);
+        self.end_frame();
+
+        //上限や時間切れで進めきれなかった分は持ち越さない(遅れを取り戻そうとして重くなり続けるのを防ぐ)
+        self.accumulator = if ticks < wanted {
+            0.0
+        } else {
+            self.accumulator - ticks as f64
+        };
+        ticks
+    }
+}
+
+//コールバックの中からSimulationのメソッドが呼ばれても借用が重ならないように、関数だけ取り出してから呼ぶ
+fn notify(controller: &Rc<RefCell<Controller>>, ticks: u32) {
+    let callback = controller.borrow().on_frame.clone();
+    if let Some(callback) = callback {
+        //js側で例外が投げられても描画ループは止めずに、コンソールに出すだけにする
+        if let Err(err) = callback.call1(&JsValue::NULL, &JsValue::from(ticks)) {
+            web_sys::console::error_2(&"Simulation: on_frame callback threw".into(), &err);
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct Simulation {
+    controller: Rc<RefCell<Controller>>,
+    started: bool,
+}
+
+impl Simulation {
+    //コントローラーを借りている間だけUniverseやフレームの差分を触らせる
+    pub(crate) fn with_universe<T>(&self, f: impl FnOnce(&Universe) -> T) -> T {
+        f(&self.controller.borrow().universe)
+    }
+    pub(crate) fn with_universe_mut<T>(&self, f: impl FnOnce(&mut Universe) -> T) -> T {
+        f(&mut self.controller.borrow_mut().universe)
+    }
+    pub(crate) fn with_frame_delta<T>(&self, f: impl FnOnce(&[Cell]) -> T) -> T {
+        f(&self.controller.borrow().frame_delta)
+    }
+}
+
+#[wasm_bindgen]
+impl Simulation {
+    pub fn new(universe: Universe) -> Simulation {
+        let cells = universe.get_cells().to_vec();
+        let controller = Controller {
+            universe,
+            speed: 60.0,
+            accumulator: 0.0,
+            max_ticks_per_frame: 64,
+            frame_budget_ms: 12.0,
+            target_generation: None,
+            playing: false,
+            notified_playing: false,
+            last_timestamp: None,
+            frame_delta: vec![Cell::Dead; cells.len()],
+            frame_start: cells,
+            on_frame: None,
+        };
+
+        Simulation {
+            controller: Rc::new(RefCell::new(controller)),
+            started: false,
+        }
+    }
+
+    //描画ループを開始する 2回目以降の呼び出しは何もしない
+    //tickしたフレームと、再生・一時停止が切り替わったフレームで、set_on_frameで渡された関数がそのフレームで進めた世代数を引数に呼ばれる
+    pub fn start(&mut self) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        main_loop(self.controller.clone());
+    }
+
+    pub fn set_on_frame(&mut self, callback: js_sys::Function) {
+        self.controller.borrow_mut().on_frame = Some(callback);
+    }
+
+    pub fn play(&mut self) {
+        let mut controller = self.controller.borrow_mut();
+        controller.playing = true;
+        controller.last_timestamp = None;
+    }
+    pub fn pause(&mut self) {
+        let mut controller = self.controller.borrow_mut();
+        controller.playing = false;
+        controller.accumulator = 0.0;
+    }
+    pub fn is_playing(&self) -> bool {
+        self.controller.borrow().playing
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.controller.borrow().speed
+    }
+    pub fn set_speed(&mut self, generations_per_second: f64) {
+        self.controller.borrow_mut().speed = generations_per_second.max(0.0);
+    }
+    pub fn set_max_ticks_per_frame(&mut self, max_ticks: u32) {
+        self.controller.borrow_mut().max_ticks_per_frame = max_ticks.max(1);
+    }
+    pub fn set_frame_budget(&mut self, milliseconds: f64) {
+        self.controller.borrow_mut().frame_budget_ms = milliseconds;
+    }
+
+    //その場でn世代進める 一時停止中のコマ送り用
+    pub fn step(&mut self, n: u32) -> u32 {
+        let ticks = {
+            let mut controller = self.controller.borrow_mut();
+            controller.begin_frame();
+            let ticks = controller.run_ticks(n, &|| false);
+            controller.end_frame();
+            ticks
+        };
+        notify(&self.controller, ticks);
+        ticks
+    }
+
+    //指定した世代まで再生して、着いたら一時停止する
+    pub fn run_until(&mut self, generation: u64) {
+        self.controller.borrow_mut().target_generation = Some(generation);
+        self.play();
+    }
+
+    //描画ループを使わずにjs側から経過時間を渡して進めるとき用 時間の上限は見ない
+    pub fn advance(&mut self, elapsed_ms: f64) -> u32 {
+        self.controller.borrow_mut().advance(elapsed_ms, &|| false)
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.controller.borrow().universe.generation()
+    }
+    pub fn width(&self) -> u32 {
+        self.controller.borrow().universe.width()
+    }
+    pub fn height(&self) -> u32 {
+        self.controller.borrow().universe.height()
+    }
+    pub fn cells(&self) -> *const Cell {
+        self.controller.borrow().universe.cells()
+    }
+    //直前のフレーム(またはstep)で変化したセル Universe::deltaと同じくAliveが変化ありを表す
+    pub fn frame_delta(&self) -> *const Cell {
+        self.controller.borrow().frame_delta.as_ptr()
+    }
+    pub fn toggle_cell(&mut self, row: u32, column: u32) {
+        self.controller
+            .borrow_mut()
+            .universe
+            .toggle_cell(row, column);
+    }
+    //ブラシはUniverseを直接受け取るので、Universeを持っているSimulation経由で渡す
+    pub fn press_brush(&mut self, brush: &mut Brush, row: u32, column: u32) {
+        brush.press(&mut self.controller.borrow_mut().universe, row, column);
+    }
+    pub fn drag_brush(&mut self, brush: &mut Brush, row: u32, column: u32) {
+        brush.drag(&mut self.controller.borrow_mut().universe, row, column);
+    }
+    pub fn reset(&mut self) {
+        let mut controller = self.controller.borrow_mut();
+        controller.universe.reset();
+        controller.target_generation = None;
+        controller.accumulator = 0.0;
+    }
+}
+
+//requestAnimationFrameに渡すクロージャを、クロージャ自身の中から次のフレームを予約できるように共有する
+type LoopClosure = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+fn main_loop(controller: Rc<RefCell<Controller>>) {
+    let closure: LoopClosure = Rc::new(RefCell::new(None));
+    let closure_clone = closure.clone();
+
+    let performance = web_sys::window().unwrap().performance().unwrap();
+
+    *closure_clone.borrow_mut() = Some(Closure::new(move |timestamp: f64| {
+        let (ticks, toggled) = {
+            let mut controller = controller.borrow_mut();
+            let elapsed = match controller.last_timestamp {
+                Some(last) => timestamp - last,
+                None => 0.0,
+            };
+            controller.last_timestamp = Some(timestamp);
+
+            let deadline = performance.now() + controller.frame_budget_ms;
+            let ticks = controller.advance(elapsed, &|| performance.now() > deadline);
+            let toggled = controller.playing != controller.notified_playing;
+            controller.notified_playing = controller.playing;
+            (ticks, toggled)
+        };
+        //run_untilで止まったフレームやplay/pauseの直後はtickが0回のこともあるので、再生状態が変わったときも知らせる
+        if ticks > 0 || toggled {
+            notify(&controller, ticks);
+        }
+        request_animation_frame(&closure);
+    }));
+
+    request_animation_frame(&closure_clone);
+}
+
+fn request_animation_frame(closure_rc: &LoopClosure) -> i32 {
+    web_sys::window()
+        .unwrap()
+        .request_animation_frame(
+            closure_rc
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .as_ref()
+                .unchecked_ref(),
+        )
+        .unwrap()
+}
@@ -0,0 +1,171 @@
+use core::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Life, Universe};
+
+//スナップショットの先頭に置く識別子とフォーマットのバージョン
+//フォーマットを変えるときはSNAPSHOT_VERSIONを上げて、古いバージョンの読み込みはload側で分岐させる
+//バージョン1はルールを持たず、いつものB3/S23として読み込む
+const SNAPSHOT_MAGIC: &[u8; 4] = b"GOLS";
+const SNAPSHOT_VERSION: u8 = 2;
+
+//ヘッダ部分の長さ magic(4) + version(1) + width(4) + height(4) + generation(8) + birth(2) + survival(2)
+const HEADER_LEN: usize = 4 + 1 + 4 + 4 + 8 + 2 + 2;
+
+//B/Sのマスクで使えるのは隣の数0〜8のビットだけ
+const RULE_MASK: u16 = 0x1ff;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidDimensions,
+    InvalidRule,
+    TrailingBytes,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "snapshot: not a universe snapshot"),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(f, "snapshot: unsupported version {}", v)
+            }
+            SnapshotError::Truncated => write!(f, "snapshot: data is truncated"),
+            SnapshotError::InvalidDimensions => write!(f, "snapshot: invalid width or height"),
+            SnapshotError::InvalidRule => write!(f, "snapshot: invalid birth/survival rule"),
+            SnapshotError::TrailingBytes => write!(f, "snapshot: unexpected data after the cells"),
+        }
+    }
+}
+
+//リトルエンディアンで数値を読み出すための小さなカーソル
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        let end = self.pos.checked_add(len).ok_or(SnapshotError::Truncated)?;
+        let slice = self.bytes.get(self.pos..end).ok_or(SnapshotError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        let mut buf = [0; 2];
+        buf.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(buf))
+    }
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+impl Universe {
+    //スナップショットのバイト列からUniverseを復元する
+    //ヘッダとセル数が合わないものは途中でindexを踏まないように全部エラーで返す
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Universe, SnapshotError> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(4)? != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != 1 && version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let generation = reader.u64()?;
+        let rule = if version == 1 {
+            Life::default()
+        } else {
+            let birth = reader.u16()?;
+            let survival = reader.u16()?;
+            if (birth | survival) & !RULE_MASK != 0 {
+                return Err(SnapshotError::InvalidRule);
+            }
+            Life { birth, survival }
+        };
+
+        let len = width
+            .checked_mul(height)
+            .filter(|&len| len > 0)
+            .ok_or(SnapshotError::InvalidDimensions)? as usize;
+
+        //セルは1セル1ビットで、先頭のセルから順にバイトの下位ビットに詰めてある
+        let packed = reader.take(len.div_ceil(8))?;
+        //後ろに余計なバイトが付いているものは、別のフォーマットか壊れたデータとみなす
+        if reader.pos != bytes.len() {
+            return Err(SnapshotError::TrailingBytes);
+        }
+        let cells = (0..len)
+            .map(|i| {
+                if packed[i / 8] >> (i % 8) & 1 == 1 {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            })
+            .collect::<Vec<Cell>>();
+
+        Ok(Universe {
+            width,
+            height,
+            next: cells.clone(),
+            cells,
+            delta: vec![Cell::Dead; len],
+            generation,
+            version: 0,
+            activity: None,
+            rule,
+        })
+    }
+
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.cells.len().div_ceil(8));
+
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        bytes.push(SNAPSHOT_VERSION);
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend_from_slice(&self.generation.to_le_bytes());
+        bytes.extend_from_slice(&self.rule.birth.to_le_bytes());
+        bytes.extend_from_slice(&self.rule.survival.to_le_bytes());
+
+        for chunk in self.cells.chunks(8) {
+            let mut byte = 0u8;
+            for (bit, &cell) in chunk.iter().enumerate() {
+                byte |= (cell as u8) << bit;
+            }
+            bytes.push(byte);
+        }
+
+        bytes
+    }
+}
+
+//js側ではUint8Arrayとして受け渡しするのでlocalStorageやファイルにそのまま保存できる
+#[wasm_bindgen]
+impl Universe {
+    pub fn save(&self) -> Vec<u8> {
+        self.to_snapshot()
+    }
+
+    pub fn load(bytes: &[u8]) -> Result<Universe, JsValue> {
+        Universe::from_snapshot(bytes).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
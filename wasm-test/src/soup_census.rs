@@ -0,0 +1,50 @@
+//ランダムなスープを大量に動かして、できた物体の種類を数えるコマンドラインツール
+//
+//使い方: soup-census [--soups N] [--seed S] [--soup-size S] [--size S] [--max-generations N]
+
+use std::{env, process};
+
+use wasm_game_of_life::Census;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: soup-census [--soups N] [--seed S] [--soup-size S] [--size S] [--max-generations N]"
+    );
+    process::exit(2);
+}
+
+fn main() {
+    let mut soups = 100;
+    let mut seed = 0;
+    let mut soup_size = 16;
+    let mut size = 64;
+    let mut max_generations = 4000;
+
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let value = iter
+            .next()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or_else(|| usage());
+
+        match arg.as_str() {
+            "--soups" => soups = value,
+            "--seed" => seed = value,
+            "--soup-size" => soup_size = value,
+            "--size" => size = value,
+            "--max-generations" => max_generations = value,
+            _ => usage(),
+        }
+    }
+
+    let mut census = Census::new(soup_size, size);
+    census.set_max_generations(max_generations);
+    census.search(seed, soups);
+
+    println!("soups: {}", census.soups());
+    println!("still lifes: {}", census.still_lifes());
+    println!("oscillators: {}", census.oscillators());
+    println!("spaceships: {}", census.spaceships());
+    println!();
+    print!("{}", census.report());
+}
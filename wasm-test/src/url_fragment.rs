@@ -0,0 +1,168 @@
+use core::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{SnapshotError, Universe};
+
+//URLのフラグメント(#以降)にUniverseの状態を埋め込むためのテキスト表現
+//スナップショットのバイト列をPackBits風のランレングスで縮めて、base64url(パディングなし)にしている
+
+//URLが長くなりすぎないようにフラグメントの文字数に上限を設ける
+const MAX_FRAGMENT_LEN: usize = 64 * 1024;
+//展開後のスナップショットの上限 ランレングスは少ない文字数で大きく膨らませられるので展開側でも制限する
+const MAX_SNAPSHOT_LEN: usize = 1024 * 1024;
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FragmentError {
+    TooLong,
+    InvalidCharacter(char),
+    InvalidLength,
+    BrokenRun,
+    TooLarge,
+    Snapshot(SnapshotError),
+}
+
+impl fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FragmentError::TooLong => write!(f, "fragment: too long"),
+            FragmentError::InvalidCharacter(c) => write!(f, "fragment: invalid character {:?}", c),
+            FragmentError::InvalidLength => write!(f, "fragment: invalid base64 length"),
+            FragmentError::BrokenRun => write!(f, "fragment: broken run-length data"),
+            FragmentError::TooLarge => write!(f, "fragment: universe is too large"),
+            FragmentError::Snapshot(e) => write!(f, "fragment: {}", e),
+        }
+    }
+}
+
+impl From<SnapshotError> for FragmentError {
+    fn from(e: SnapshotError) -> FragmentError {
+        FragmentError::Snapshot(e)
+    }
+}
+
+fn base64url_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        //3バイト(24ビット)を6ビットずつ4文字にする 端数のチャンクは必要な文字数だけ出す
+        for i in 0..chunk.len() + 1 {
+            out.push(BASE64URL[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+fn base64url_decode(text: &str) -> Result<Vec<u8>, FragmentError> {
+    //4文字ごとに3バイトになるので、余りが1文字だけになることはない
+    if text.len() % 4 == 1 {
+        return Err(FragmentError::InvalidLength);
+    }
+
+    let mut out = Vec::with_capacity(text.len() / 4 * 3 + 2);
+    for chunk in text.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64URL
+                .iter()
+                .position(|&b| b == c)
+                .ok_or(FragmentError::InvalidCharacter(c as char))?;
+            n |= (value as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(out)
+}
+
+//PackBits風のランレングス圧縮
+//先頭バイトnが0〜127ならその後にn+1バイトの生データ、128〜255なら次の1バイトをn-125回(3〜130回)繰り返す
+fn pack_runs(bytes: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut literal_start = 0;
+    let mut i = 0;
+
+    let flush_literal = |out: &mut Vec<u8>, literal: &[u8]| {
+        for chunk in literal.chunks(128) {
+            out.push((chunk.len() - 1) as u8);
+            out.extend_from_slice(chunk);
+        }
+    };
+
+    while i < bytes.len() {
+        let mut run = 1;
+        while i + run < bytes.len() && bytes[i + run] == bytes[i] && run < 130 {
+            run += 1;
+        }
+
+        //3回以上続いたときだけ繰り返しとして書き出す それより短いと逆に長くなる
+        if run >= 3 {
+            flush_literal(&mut out, &bytes[literal_start..i]);
+            out.push((run + 125) as u8);
+            out.push(bytes[i]);
+            i += run;
+            literal_start = i;
+        } else {
+            i += run;
+        }
+    }
+    flush_literal(&mut out, &bytes[literal_start..]);
+
+    out
+}
+
+fn unpack_runs(bytes: &[u8]) -> Result<Vec<u8>, FragmentError> {
+    let mut out = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let header = bytes[i] as usize;
+        i += 1;
+
+        if header < 128 {
+            let literal = bytes.get(i..i + header + 1).ok_or(FragmentError::BrokenRun)?;
+            out.extend_from_slice(literal);
+            i += header + 1;
+        } else {
+            let &byte = bytes.get(i).ok_or(FragmentError::BrokenRun)?;
+            out.resize(out.len() + header - 125, byte);
+            i += 1;
+        }
+
+        if out.len() > MAX_SNAPSHOT_LEN {
+            return Err(FragmentError::TooLarge);
+        }
+    }
+    Ok(out)
+}
+
+impl Universe {
+    //フラグメント文字列からUniverseを復元する 先頭の#はあってもなくても良い
+    pub fn parse_url_fragment(fragment: &str) -> Result<Universe, FragmentError> {
+        let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+        if fragment.len() > MAX_FRAGMENT_LEN {
+            return Err(FragmentError::TooLong);
+        }
+
+        let packed = base64url_decode(fragment)?;
+        let snapshot = unpack_runs(&packed)?;
+        Ok(Universe::from_snapshot(&snapshot)?)
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    pub fn to_url_fragment(&self) -> String {
+        base64url_encode(&pack_runs(&self.to_snapshot()))
+    }
+
+    pub fn from_url_fragment(fragment: &str) -> Result<Universe, JsValue> {
+        Universe::parse_url_fragment(fragment).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
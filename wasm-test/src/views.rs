@@ -0,0 +1,78 @@
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Simulation, Universe};
+
+//cells()やdelta()のポインタからjs側でUint8Arrayを作るやり方は、wasmのメモリが伸びるとmemory.bufferごと差し替わって
+//古いビューが黙って空になる(長さ0になる)ので、ビューをRust側で作って返す口と、js側の配列にコピーする口を用意する
+//
+//ビューを使い回すときは、version()が変わったときとview.bufferがmemory.bufferと違うときに作り直す
+//コピーの方はjs側の配列に書き込むのでメモリが伸びても壊れない
+
+//Cellはrepr(u8)なので、同じ長さのu8の列としてそのまま読める
+fn as_bytes(cells: &[Cell]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(cells.as_ptr() as *const u8, cells.len()) }
+}
+
+//wasmのメモリを直接指すビュー 次にRust側でメモリ確保が起きるまでしか有効でない
+fn view(cells: &[Cell]) -> Uint8Array {
+    unsafe { Uint8Array::view(as_bytes(cells)) }
+}
+
+//長さが違う配列にはコピーしない(盤面の大きさが変わったのにjs側が古い配列を使っているときに気づけるように)
+fn copy_into(cells: &[Cell], buffer: &mut [u8]) -> Result<(), JsValue> {
+    if buffer.len() != cells.len() {
+        return Err(JsValue::from_str(&format!(
+            "buffer length {} does not match cell count {}",
+            buffer.len(),
+            cells.len()
+        )));
+    }
+    buffer.copy_from_slice(as_bytes(cells));
+    Ok(())
+}
+
+#[wasm_bindgen]
+impl Universe {
+    //cellsとdeltaの配列を作り直した回数 reset,set_width,set_heightで増える
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn cells_view(&self) -> Uint8Array {
+        view(&self.cells)
+    }
+    pub fn delta_view(&self) -> Uint8Array {
+        view(&self.delta)
+    }
+
+    //js側で用意したwidth*height長のUint8Arrayにセルを書き込む
+    pub fn copy_cells_into(&self, buffer: &mut [u8]) -> Result<(), JsValue> {
+        copy_into(&self.cells, buffer)
+    }
+    pub fn copy_delta_into(&self, buffer: &mut [u8]) -> Result<(), JsValue> {
+        copy_into(&self.delta, buffer)
+    }
+}
+
+#[wasm_bindgen]
+impl Simulation {
+    //Universe::versionと同じ フレームの差分の配列は盤面と同じ大きさのまま使い回すので一緒に扱える
+    pub fn version(&self) -> u32 {
+        self.with_universe(|universe| universe.version())
+    }
+
+    pub fn cells_view(&self) -> Uint8Array {
+        self.with_universe(|universe| view(&universe.cells))
+    }
+    pub fn frame_delta_view(&self) -> Uint8Array {
+        self.with_frame_delta(view)
+    }
+
+    pub fn copy_cells_into(&self, buffer: &mut [u8]) -> Result<(), JsValue> {
+        self.with_universe(|universe| copy_into(&universe.cells, buffer))
+    }
+    pub fn copy_frame_delta_into(&self, buffer: &mut [u8]) -> Result<(), JsValue> {
+        self.with_frame_delta(|delta| copy_into(delta, buffer))
+    }
+}
@@ -0,0 +1,336 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+//ブラウザを使わずにcargo testで確かめられるテスト
+//wasm_bindgenの関数のうち、JsValueを作らないものだけをここで試す
+
+use wasm_game_of_life::{
+    Automaton, Cell, Cyclic, Elementary, GifError, GifOptions, Lenia, Life, Pattern, PatternError,
+    Rule, RuleKind, SnapshotError, Universe, MAX_ACTIVITY_WINDOW, MAX_GIF_GENERATIONS,
+    MAX_GIF_PIXELS,
+};
+
+fn approx(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-4
+}
+
+//半径2のカーネルは距離1の4セル(r=0.5)と距離√2の4セル(r≈0.707)だけが0<r<1に入る
+//重みはexp(4-1/(r(1-r)))で、r=0.5は1、r=1/√2は1/(r(1-r))=2+2√2なのでexp(2-2√2)≈0.43674
+//合計で割ると上下左右が1/(4+4*0.43674)≈0.17401、斜めが0.43674/(4+4*0.43674)≈0.07599になる
+//中央に1.0を1つだけ置いて1ステップ進め、成長関数2exp(-(p-mu)^2/(2sigma^2))-1の結果と比べる
+#[test]
+fn lenia_step_matches_hand_computed_values() {
+    let mut lenia = Lenia::new(8, 8);
+    lenia.set_radius(2);
+    lenia.set_mu(0.17);
+    lenia.set_sigma(0.02);
+    lenia.set_dt(0.5);
+    for row in 0..8 {
+        for col in 0..8 {
+            lenia.set_cell(row, col, 0.0);
+        }
+    }
+    lenia.set_cell(3, 3, 1.0);
+
+    lenia.tick();
+    let cells = lenia.get_cells();
+    let at = |row: usize, col: usize| cells[row * 8 + col];
+
+    //中央: ポテンシャル0なので成長は-1 1.0+0.5*(-1)=0.5
+    assert!(approx(at(3, 3), 0.5), "{}", at(3, 3));
+    //上下左右: ポテンシャル0.17401 成長2exp(-(0.00401)^2/0.0008)-1≈0.96029 0.5倍して0.48014
+    for &(row, col) in &[(2, 3), (4, 3), (3, 2), (3, 4)] {
+        assert!(approx(at(row, col), 0.48014), "{}", at(row, col));
+    }
+    //斜め: ポテンシャル0.07599はmuから遠く成長はほぼ-1なので0に切り詰められる
+    for &(row, col) in &[(2, 2), (2, 4), (4, 2), (4, 4)] {
+        assert_eq!(at(row, col), 0.0);
+    }
+    //カーネルの届かないところは0のまま
+    assert_eq!(at(3, 5), 0.0);
+    assert_eq!(at(0, 0), 0.0);
+}
+
+//幅か高さが0のUniverseや、上限を超える世代数はpanicせずにエラーになる
+#[test]
+fn encode_gif_rejects_empty_universe_and_too_many_generations() {
+    let mut universe = Universe::new();
+    universe.set_width(0);
+    assert!(matches!(
+        universe.encode_gif(&GifOptions::default()),
+        Err(GifError::Empty)
+    ));
+
+    let mut universe = Universe::new();
+    universe.set_width(4);
+    universe.set_height(4);
+    let options = GifOptions {
+        generations: MAX_GIF_GENERATIONS + 1,
+        ..GifOptions::default()
+    };
+    assert!(matches!(
+        universe.encode_gif(&options),
+        Err(GifError::TooManyGenerations)
+    ));
+    let options = GifOptions {
+        generations: 2,
+        ..GifOptions::default()
+    };
+    assert_eq!(&universe.encode_gif(&options).unwrap()[..6], b"GIF89a");
+}
+
+//繰り返し回数がu32の上限でも、足し算があふれてpanicせずにはみ出しのエラーになる
+#[test]
+fn rle_rejects_overflowing_run_counts() {
+    assert_eq!(
+        Pattern::parse_rle("x = 3, y = 3\n4294967295b2o!"),
+        Err(PatternError::OutOfBounds)
+    );
+    assert_eq!(
+        Pattern::parse_rle("x = 3, y = 3\n2o4294967295$o!"),
+        Err(PatternError::OutOfBounds)
+    );
+}
+
+//jsから大きな座標を渡されても回り込んで配置され、セルが無いUniverseでもpanicしない
+#[test]
+fn place_pattern_wraps_large_coordinates() {
+    let glider = Pattern::parse_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+
+    let mut universe = Universe::with_size(4, 4);
+    universe.place_pattern(&glider, u32::MAX, u32::MAX);
+    //u32::MAXは4で割ると3余るので、左上が(3,3)に来る
+    let alive: Vec<(u32, u32)> = (0..16)
+        .filter(|&i| universe.get_cells()[i as usize] == Cell::Alive)
+        .map(|i| (i / 4, i % 4))
+        .collect();
+    assert_eq!(alive, vec![(0, 1), (1, 0), (1, 1), (1, 3), (3, 0)]);
+
+    for &(width, height) in &[(0, 5), (5, 0), (0, 0)] {
+        let mut universe = Universe::with_size(width, height);
+        universe.place_pattern(&glider, 1, 1);
+        assert_eq!(universe.population(), 0);
+    }
+}
+
+#[test]
+fn life_rule_parses_and_prints_bs_notation() {
+    assert_eq!("B3/S23".parse::<Life>(), Ok(Life::default()));
+    assert_eq!("s23/b3".parse::<Life>(), Ok(Life::default()));
+    let highlife = "B36/S23".parse::<Life>().unwrap();
+    assert_eq!(highlife.to_string(), "B36/S23");
+    assert_eq!("B/S".parse::<Life>().unwrap().to_string(), "B/S");
+    for bad in &["B3", "B9/S23", "B3/S23/B3", "X3/S23", ""] {
+        assert!(bad.parse::<Life>().is_err(), "{}", bad);
+    }
+}
+
+//ルールを変えるとtickの結果が変わり、RLEにもそのルールが書き出される
+#[test]
+fn universe_ticks_with_chosen_rule() {
+    //ooo/..o/oo.の真ん中は死んでいて隣が6個なので、B3/S23では生まれずB36/S23では生まれる
+    let shape = Pattern::parse_rle("x = 3, y = 3\n3o$2bo$2o!").unwrap();
+    let run = |rule: &str| {
+        let mut universe = Universe::with_size(8, 8);
+        universe.set_rule(rule.parse::<Life>().unwrap());
+        universe.place_pattern(&shape, 2, 2);
+        universe.tick();
+        universe
+    };
+    let center = 3 * 8 + 3;
+
+    let life = run("B3/S23");
+    assert_eq!(life.get_cells()[center], Cell::Dead);
+    assert!(life.rle().contains("rule = B3/S23"));
+
+    let highlife = run("B36/S23");
+    assert_eq!(highlife.get_cells()[center], Cell::Alive);
+    assert!(highlife.rle().contains("rule = B36/S23"));
+}
+
+//幅や高さに0を渡しても1に丸められ、resetやtickで落ちない
+#[test]
+fn elementary_clamps_zero_dimensions() {
+    let mut elementary = Elementary::new(30, 0, 0);
+    assert_eq!((elementary.width(), elementary.height()), (1, 1));
+    assert_eq!(elementary.get_cells(), &[Cell::Alive]);
+
+    //Rule 30で左・自分・右がすべて生きている(111)と次は死ぬ
+    elementary.tick();
+    assert_eq!(elementary.get_cells(), &[Cell::Dead]);
+    assert_eq!(elementary.generation(), 1);
+}
+
+//状態が2つ未満の巡回セルオートマトンは2状態に丸められ、toggle_cellで0除算しない
+#[test]
+fn cyclic_needs_at_least_two_states() {
+    assert_eq!(Cyclic::new(0, 1).states(), 2);
+    assert_eq!(Cyclic::new(1, 1).states(), 2);
+
+    let mut automaton =
+        Automaton::with_rule(Box::new(Cyclic::new(0, 1)), RuleKind::Cyclic, 4, 4).unwrap();
+    automaton.toggle_cell(0, 0);
+    assert_eq!(automaton.get_state(0, 0), 1);
+    automaton.toggle_cell(0, 0);
+    assert_eq!(automaton.get_state(0, 0), 0);
+    automaton.tick();
+}
+
+//Automatonのライフゲームと、UniverseのB3/S23は同じ隣の数え方なので同じ結果になる
+#[test]
+fn life_rule_on_grid_matches_universe() {
+    let glider = Pattern::parse_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+    let mut universe = Universe::with_size(6, 6);
+    //端をまたいで回り込むように置く
+    universe.place_pattern(&glider, 4, 4);
+
+    let mut automaton = Automaton::new(RuleKind::Life, 6, 6);
+    for (i, &cell) in universe.get_cells().iter().enumerate() {
+        if cell == Cell::Alive {
+            automaton.set_state(i as u32 / 6, i as u32 % 6, 1);
+        }
+    }
+
+    for _ in 0..8 {
+        universe.tick();
+        automaton.tick();
+        let states: Vec<u8> = universe.get_cells().iter().map(|&c| c as u8).collect();
+        assert_eq!(automaton.grid().cells, states);
+    }
+}
+
+//窓は上限に丸められ、変化回数は窓の長さより増えない
+#[test]
+fn activity_window_is_capped_and_slides() {
+    let mut universe = Universe::with_size(8, 8);
+    universe.enable_activity(u32::MAX);
+    assert_eq!(universe.activity_window(), MAX_ACTIVITY_WINDOW);
+
+    //ブリンカーの端のセルは毎世代変化し、中央のセルは変化しない
+    universe.set_cells(&[(3, 2), (3, 3), (3, 4)]);
+    universe.enable_activity(3);
+    let index = |row: usize, col: usize| row * 8 + col;
+    for expected in [1, 2, 3, 3, 3] {
+        universe.tick();
+        let counts = universe.get_activity().unwrap();
+        assert_eq!(counts[index(3, 2)], expected);
+        assert_eq!(counts[index(2, 3)], expected);
+        assert_eq!(counts[index(3, 3)], 0);
+    }
+}
+
+//B3/S23以外のルールもスナップショットとURLフラグメントを通して元に戻る
+#[test]
+fn snapshot_round_trips_non_default_rule() {
+    let mut universe = Universe::with_size(8, 6);
+    universe.set_rule("B36/S23".parse::<Life>().unwrap());
+    universe.set_cells(&[(1, 1), (1, 2), (2, 3), (4, 5)]);
+    universe.tick();
+
+    let restored = Universe::from_snapshot(&universe.to_snapshot()).unwrap();
+    assert_eq!(restored.rule(), universe.rule());
+    assert_eq!(restored.generation(), 1);
+    assert_eq!(restored.get_cells(), universe.get_cells());
+
+    let restored = Universe::parse_url_fragment(&universe.to_url_fragment()).unwrap();
+    assert_eq!(restored.rule().to_string(), "B36/S23");
+    assert_eq!(restored.get_cells(), universe.get_cells());
+}
+
+//後ろに余計なバイトがあるものや、隣の数が9以上のビットが立ったルールは読み込まない
+#[test]
+fn snapshot_rejects_trailing_bytes_and_bad_rules() {
+    let universe = Universe::with_size(4, 4);
+    let mut bytes = universe.to_snapshot();
+    bytes.push(0);
+    assert_eq!(
+        Universe::from_snapshot(&bytes).err(),
+        Some(SnapshotError::TrailingBytes)
+    );
+
+    //ヘッダのbirthはmagic(4)+version(1)+width(4)+height(4)+generation(8)の後ろ
+    let mut bytes = universe.to_snapshot();
+    bytes[21..23].copy_from_slice(&(1u16 << 9).to_le_bytes());
+    assert_eq!(
+        Universe::from_snapshot(&bytes).err(),
+        Some(SnapshotError::InvalidRule)
+    );
+}
+
+//1つ1つの辺はGIFに収まっても、全フレームの画素数が上限を超えるものはエンコードする前に断る
+#[test]
+fn encode_gif_caps_total_pixels() {
+    let universe = Universe::with_size(1024, 1024);
+    let gif = |generations: u32, cell_size: u32| {
+        universe.encode_gif(&GifOptions {
+            generations,
+            cell_size,
+            ..GifOptions::default()
+        })
+    };
+
+    //1フレーム約100万画素なので、129フレームで上限(2^27)を超える
+    assert_eq!(MAX_GIF_PIXELS, 1024 * 1024 * 128);
+    assert!(matches!(gif(128, 1), Err(GifError::TooManyPixels)));
+    //64512x64512はu16に収まるが、1フレームだけで上限を超える
+    assert!(matches!(gif(0, 63), Err(GifError::TooManyPixels)));
+    assert!(matches!(gif(0, 64), Err(GifError::TooLarge)));
+}
+
+//tickはセルとdeltaの配列を使い回すが、書き足している間もスクロールし始めてからも、deltaは前の世代との差になっている
+#[test]
+fn elementary_tick_reuses_buffers_and_reports_delta() {
+    let mut elementary = Elementary::new(30, 16, 4);
+    let cells_ptr = elementary.cells();
+    let delta_ptr = elementary.delta();
+    for _ in 0..10 {
+        let before = elementary.get_cells().to_vec();
+        let next_row = elementary.next_row();
+        elementary.tick();
+
+        let after = elementary.get_cells();
+        let expected: Vec<Cell> = before
+            .iter()
+            .zip(after.iter())
+            .map(|(a, b)| if a != b { Cell::Alive } else { Cell::Dead })
+            .collect();
+        assert_eq!(elementary.get_delta(), expected.as_slice());
+        //書いた行は最後に書き込んだ行になっている
+        let rows = (elementary.generation() as usize + 1).min(4);
+        assert_eq!(&after[(rows - 1) * 16..rows * 16], next_row.as_slice());
+    }
+    assert_eq!(elementary.cells(), cells_ptr);
+    assert_eq!(elementary.delta(), delta_ptr);
+}
+
+//状態が2つ未満のルールや、パレットの色が足りないルールはpanicせずにエラーになる
+#[test]
+fn automaton_rejects_invalid_custom_rules() {
+    use wasm_game_of_life::Grid;
+
+    struct Custom {
+        states: u8,
+        colors: usize,
+    }
+    impl Rule for Custom {
+        fn states(&self) -> u8 {
+            self.states
+        }
+        fn palette(&self) -> Vec<u32> {
+            vec![0; self.colors]
+        }
+        fn next_state(&self, grid: &Grid, row: u32, column: u32) -> u8 {
+            grid.cells[grid.get_index(row, column)]
+        }
+    }
+    let build = |states: u8, colors: usize| {
+        Automaton::with_rule(Box::new(Custom { states, colors }), RuleKind::Life, 4, 4)
+    };
+
+    assert!(build(0, 0).is_err());
+    assert!(build(1, 1).is_err());
+    assert_eq!(
+        build(3, 2).err().unwrap(),
+        "automaton: palette has 2 colors for 3 states"
+    );
+    assert_eq!(build(3, 3).unwrap().states(), 3);
+}
@@ -0,0 +1,56 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+//tests/scenarios以下の.lifeファイルを全部Scenarioで実行する
+//シナリオを足すときはファイルを置くだけで良い
+
+use std::fs;
+use std::path::Path;
+
+use wasm_game_of_life::Scenario;
+
+#[test]
+fn run_scenarios() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "life"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let mut failures = vec![];
+    for path in paths.iter() {
+        let script = fs::read_to_string(path).unwrap();
+        let mut scenario = Scenario::new();
+        if let Err(e) = scenario.run(&script) {
+            failures.push(format!("{}: {}", path.display(), e));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+//失敗したときに何行目のどの命令かが分かるかを確かめる
+#[test]
+fn report_failing_line() {
+    use wasm_game_of_life::ScenarioError;
+
+    let mut scenario = Scenario::new();
+    let result = scenario.run("size 8 8\nassert population 0\nassert alive 1 1\n");
+    assert!(matches!(result, Err(ScenarioError::Assertion(3, _))));
+    assert_eq!(scenario.assertions(), 1);
+
+    let mut scenario = Scenario::new();
+    assert_eq!(
+        scenario.run("place glider at 0 0"),
+        Err(ScenarioError::UnknownPattern(1, String::from("glider")))
+    );
+    assert!(matches!(
+        Scenario::new().run("pattern block\nOO\nOO\n"),
+        Err(ScenarioError::Syntax(1, _))
+    ));
+    assert!(matches!(
+        Scenario::new().run("size 8 8\nrule B9/S23"),
+        Err(ScenarioError::Syntax(2, _))
+    ));
+}
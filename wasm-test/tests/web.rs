@@ -9,7 +9,10 @@ use wasm_bindgen_test::*;
 
 //外部のwasm_game_of_lifeクレートを利用しますよ宣言
 extern crate wasm_game_of_life;
-use wasm_game_of_life::Universe;
+use wasm_game_of_life::{
+    bresenham_line, identify, Automaton, Brush, BrushMode, Cell, Census, Elementary, FragmentError,
+    GifOptions, Lenia, ObjectKind, Pattern, RuleKind, Simulation, SnapshotError, Universe,
+};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -45,3 +48,281 @@ pub fn expected_spaceship() -> Universe {
     universe.set_cells(&[(2, 1), (2, 3), (3, 2), (3, 3), (4, 2)]);
     universe
 }
+
+//Leniaのrender_rgbaがcanvasのImageDataにそのまま渡せる長さ(1セル4バイト)になっているかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_lenia_render_rgba() {
+    let lenia = Lenia::new(32, 16);
+    let rgba = lenia.render_rgba();
+
+    assert_eq!(rgba.len(), 32 * 16 * 4);
+    assert!(rgba.chunks(4).all(|px| px[3] == 255));
+}
+
+//saveしたスナップショットをloadすると同じ状態のUniverseに戻るかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_snapshot_roundtrip() {
+    let mut universe = input_spaceship();
+    universe.tick();
+
+    let restored = Universe::from_snapshot(&universe.save()).unwrap();
+    assert_eq!(restored.width(), 6);
+    assert_eq!(restored.height(), 6);
+    assert_eq!(restored.generation(), 1);
+    assert_eq!(restored.get_cells(), universe.get_cells());
+}
+
+//壊れたスナップショットはpanicせずにエラーになるかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_snapshot_rejects_truncated() {
+    let bytes = input_spaceship().save();
+
+    assert_eq!(
+        Universe::from_snapshot(&bytes[..bytes.len() - 1]).err(),
+        Some(SnapshotError::Truncated)
+    );
+    assert_eq!(
+        Universe::from_snapshot(b"nope").err(),
+        Some(SnapshotError::BadMagic)
+    );
+}
+
+//URLフラグメントに書き出した状態から同じUniverseが復元できるか、壊れた文字列がエラーになるかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_url_fragment() {
+    let universe = input_spaceship();
+    let fragment = universe.to_url_fragment();
+
+    let restored = Universe::parse_url_fragment(&format!("#{}", fragment)).unwrap();
+    assert_eq!(restored.get_cells(), universe.get_cells());
+
+    assert_eq!(
+        Universe::parse_url_fragment("R09MUw!").err(),
+        Some(FragmentError::InvalidCharacter('!'))
+    );
+    assert!(Universe::parse_url_fragment(&fragment[..fragment.len() - 2]).is_err());
+}
+
+//GIFに書き出したときに元のUniverseが進まず、GIFのヘッダと指定したサイズになっているかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_encode_gif() {
+    let universe = input_spaceship();
+    let options = GifOptions {
+        generations: 4,
+        cell_size: 2,
+        ..GifOptions::default()
+    };
+
+    let gif = universe.encode_gif(&options).unwrap();
+    assert_eq!(&gif[..6], b"GIF89a");
+    assert_eq!(u16::from_le_bytes([gif[6], gif[7]]), 12);
+    assert_eq!(u16::from_le_bytes([gif[8], gif[9]]), 12);
+    assert_eq!(universe.generation(), 0);
+}
+
+//RLEで読み込んだグライダーを配置して、RLEに書き出すと同じ文字列に戻るかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_rle_roundtrip() {
+    let glider = Pattern::parse("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n").unwrap();
+    assert_eq!(glider.cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+
+    let mut universe = Universe::with_size(8, 8);
+    universe.place_pattern(&glider, 2, 3);
+    assert_eq!(universe.population(), 5);
+    assert_eq!(universe.rle(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+}
+
+//代表的な物体がapgsearchと同じ表記で見分けられるかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_identify_objects() {
+    let block = identify(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+    assert_eq!(block.kind, ObjectKind::StillLife);
+    assert_eq!(block.code, "xs4_33");
+
+    let blinker = identify(&[(0, 0), (0, 1), (0, 2)]);
+    assert_eq!(blinker.kind, ObjectKind::Oscillator);
+    assert_eq!(blinker.code, "xp2_7");
+
+    let glider = identify(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+    assert_eq!(glider.kind, ObjectKind::Spaceship);
+    assert_eq!(glider.code, "xq4_153");
+    assert_eq!((glider.period, glider.dx, glider.dy), (4, 1, 1));
+}
+
+//同じシードのスープからは同じ集計結果が出るかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_census_is_deterministic() {
+    let mut a = Census::new(16, 64);
+    let mut b = Census::new(16, 64);
+    a.search(0, 3);
+    b.search(0, 3);
+
+    assert_eq!(a.soups(), 3);
+    assert_eq!(a.report(), b.report());
+}
+
+//盤面上のブロックとグライダーが位置と名前付きで見分けられるかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_classify() {
+    let mut universe = Universe::with_size(16, 16);
+    universe.set_cells(&[(1, 1), (1, 2), (2, 1), (2, 2)]);
+    universe.set_cells(&[(8, 9), (9, 10), (10, 8), (10, 9), (10, 10)]);
+
+    let mut objects = universe.classify();
+    objects.sort_by_key(|o| o.row());
+
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects[0].name(), "block");
+    assert_eq!((objects[0].row(), objects[0].column()), (1, 1));
+    assert_eq!(objects[1].name(), "glider heading SE");
+    assert_eq!((objects[1].width(), objects[1].height()), (3, 3));
+}
+
+//速度に応じて1フレームで進む世代数が変わり、run_untilで指定した世代に着いたら止まるかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_simulation_frame_budget() {
+    let mut simulation = Simulation::new(Universe::with_size(16, 16));
+    simulation.set_speed(120.0);
+
+    //一時停止中は進まない
+    assert_eq!(simulation.advance(1000.0 / 60.0), 0);
+
+    simulation.play();
+    assert_eq!(simulation.advance(1000.0 / 60.0), 2);
+    assert_eq!(simulation.advance(100.0), 12);
+
+    simulation.run_until(20);
+    assert_eq!(simulation.advance(1000.0), 6);
+    assert_eq!(simulation.generation(), 20);
+    assert!(!simulation.is_playing());
+}
+
+//ドラッグの間のセルが直線で埋まり、消しゴムで消せるかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_brush_drag() {
+    assert_eq!(
+        bresenham_line(0, 0, 2, 4),
+        vec![(0, 0), (1, 1), (1, 2), (2, 3), (2, 4)]
+    );
+
+    let mut universe = Universe::with_size(16, 16);
+    let mut brush = Brush::new();
+    brush.press(&mut universe, 1, 1);
+    brush.drag(&mut universe, 1, 10);
+    brush.release();
+    assert_eq!(universe.population(), 10);
+
+    brush.set_mode(BrushMode::Erase);
+    brush.set_size(3);
+    brush.press(&mut universe, 1, 5);
+    brush.release();
+    assert_eq!(universe.population(), 7);
+}
+
+//Rule 90で中央の1セルからシェルピンスキーの三角形ができ、下まで埋まったらスクロールするかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_elementary_rule_90() {
+    let mut elementary = Elementary::new(90, 7, 3);
+    elementary.tick();
+    elementary.tick();
+
+    let rows: Vec<String> = elementary
+        .get_cells()
+        .chunks(7)
+        .map(|row| {
+            row.iter()
+                .map(|&c| if c == Cell::Alive { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    assert_eq!(rows, vec!["...#...", "..#.#..", ".#...#."]);
+
+    elementary.tick();
+    let last_row: String = elementary.get_cells()[14..]
+        .iter()
+        .map(|&c| if c == Cell::Alive { '#' } else { '.' })
+        .collect();
+    assert_eq!(last_row, "#.#.#.#");
+    assert_eq!(elementary.generation(), 3);
+}
+
+//Wireworldの導線の上を電子が1マスずつ進み、toggle_cellで状態が一周するかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_wireworld_electron() {
+    let mut automaton = Automaton::new(RuleKind::Wireworld, 8, 3);
+    for col in 0..8 {
+        automaton.set_state(1, col, 3);
+    }
+    automaton.set_state(1, 2, 2);
+    automaton.set_state(1, 3, 1);
+
+    automaton.tick();
+    assert_eq!(
+        (0..8)
+            .map(|c| automaton.get_state(1, c))
+            .collect::<Vec<u8>>(),
+        vec![3, 3, 3, 2, 1, 3, 3, 3]
+    );
+
+    automaton.toggle_cell(0, 0);
+    assert_eq!(automaton.get_state(0, 0), 1);
+    for _ in 0..3 {
+        automaton.toggle_cell(0, 0);
+    }
+    assert_eq!(automaton.get_state(0, 0), 0);
+}
+
+//コピー用の口がjs側の配列にセルを書き込み、配列を作り直したときだけversionが増えるかを確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_copy_cells_and_version() {
+    let mut universe = Universe::with_size(4, 4);
+    universe.toggle_cell(1, 2);
+
+    let mut buffer = vec![0u8; 16];
+    universe.copy_cells_into(&mut buffer).unwrap();
+    assert_eq!(buffer[universe.width() as usize + 2], Cell::Alive as u8);
+    assert_eq!(buffer.iter().filter(|&&b| b != 0).count(), 1);
+
+    //tickでは配列を使い回すのでversionは変わらない
+    let version = universe.version();
+    universe.tick();
+    universe.copy_delta_into(&mut buffer).unwrap();
+    assert_eq!(buffer[universe.width() as usize + 2], Cell::Alive as u8);
+    assert_eq!(universe.version(), version);
+
+    universe.set_width(8);
+    assert_ne!(universe.version(), version);
+    assert!(universe.copy_cells_into(&mut buffer).is_err());
+}
+
+//ブリンカーの端のセルは毎世代変化し、中央のセルは一度も変化しないことを変化回数で確かめるテスト
+#[wasm_bindgen_test]
+pub fn test_activity_heatmap() {
+    let mut universe = Universe::with_size(8, 8);
+    universe.set_cells(&[(3, 2), (3, 3), (3, 4)]);
+    assert!(universe.get_activity().is_none());
+
+    universe.enable_activity(4);
+    for _ in 0..6 {
+        universe.tick();
+    }
+    let counts = universe.get_activity().unwrap();
+    let index = |row: usize, col: usize| row * 8 + col;
+    //窓は4世代分なので6世代進めても4回まで
+    assert_eq!(counts[index(3, 2)], 4);
+    assert_eq!(counts[index(2, 3)], 4);
+    assert_eq!(counts[index(3, 3)], 0);
+    assert_eq!(counts[index(0, 0)], 0);
+
+    //毎世代変化するセルは赤、変化しない生きたセルは黒で描かれる
+    let rgba = universe.render_heatmap_rgba();
+    assert_eq!(
+        &rgba[index(3, 2) * 4..index(3, 2) * 4 + 4],
+        &[255, 0, 0, 255]
+    );
+    assert_eq!(&rgba[index(3, 3) * 4..index(3, 3) * 4 + 4], &[0, 0, 0, 255]);
+
+    universe.reset();
+    assert_eq!(universe.activity_window(), 4);
+    assert!(universe.get_activity().unwrap().iter().all(|&c| c == 0));
+}
@@ -1,16 +1,14 @@
-import { Universe, Cell } from "wasm-game-of-life";
-
-//wasm_bindgenによって生成されるwasm線形メモリ空間への橋渡しをするオブジェクト
-import { memory } from "wasm-game-of-life/wasm_game_of_life_bg"
+import { Universe, Simulation, Brush, BrushShape, BrushMode, Cell } from "wasm-game-of-life";
 
 const CELL_SIZE = 3; // px
 const GRID_COLOR = "#CCCCCC";
 const DEAD_COLOR = "#FFFFFF";
 const ALIVE_COLOR = "#000000";
 
-const universe = Universe.new();
-const width = universe.width();
-const height = universe.height();
+//Universeの所有権はSimulationに移るので、以降のセル操作はsimulation経由で行う
+const simulation = Simulation.new(Universe.new());
+const width = simulation.width();
+const height = simulation.height();
 
 const canvas = document.querySelector("canvas");
 canvas.height = (CELL_SIZE + 1) * height + 1;
@@ -19,18 +17,11 @@ canvas.width = (CELL_SIZE + 1) * width + 1;
 
 const ctx = canvas.getContext('2d');
 
-let cellMem = new Array(Uint8Array.length);
+//描画済みのセルの状態 js側の配列なのでwasmのメモリが伸びても壊れない
+let cellMem = new Uint8Array(width * height);
 
 const initCells = () => {
-    const cellsPtr = universe.cells();
-    const cells = new Uint8Array(memory.buffer, cellsPtr, width * height);
-
-    for (let i = 0; i < cells.length; i++) {
-        cellMem[i] = cells[i];
-
-    }
-
-
+    simulation.copy_cells_into(cellMem);
 }
 
 const drawGrid = () => {
@@ -58,14 +49,10 @@ const getIndex = (row, column) => {
 }
 
 const drawCells = () => {
-    //cellsの先頭を示すポインタを得る
-    const cellsPtr = universe.cells();
-
     //cellsの状態を示す配列を得る
-    //ここちょっと複雑で、やってることはwasmのメモリ空間から指定したアドレス上の生のバイナリ列を取ってきてUint8として認識できるような配列に格納……って感じ
-    //memory.bufferはjs側からwasmメモリ空間にアクセスするための窓口 メモリ空間すべてを表す
-    //で、そこからcellsPtrアドレスから(width * height)長のバイナリを取得して配列に格納し、uint8型の配列だよって宣言するという流れ
-    const cells = new Uint8Array(memory.buffer, cellsPtr, width * height);
+    //wasmのメモリ空間をそのまま指すビューで、wasmのメモリが伸びると無効になるので描画のたびにRust側で作り直してもらう
+    //(ポインタからmemory.bufferを切り出すと、メモリが伸びたときに古いbufferを見たまま黙って空になる)
+    const cells = simulation.cells_view();
 
     ctx.beginPath();
 
@@ -111,8 +98,8 @@ const drawCells = () => {
 
 const drawCellsDelta = () => {
 
-    const deltaPtr = universe.delta();
-    const delta = new Uint8Array(memory.buffer, deltaPtr, width * height);
+    //1フレームで複数世代進むことがあるので、Universe::deltaではなくフレーム全体の差分を使う
+    const delta = simulation.frame_delta_view();
 
 
     ctx.beginPath();
@@ -159,22 +146,65 @@ const drawBG = () => {
     )
 }
 
+//ヒートマップ表示 セルごとの直近の変化回数をRust側で色にしてもらい、1セル1ピクセルの画像をセルの大きさに拡大して描く
+//振動子のある場所が赤く、何も起きていない場所は普段の白黒のまま残る
+const renderModeSelect = document.getElementById("render-mode");
+const activityWindowInput = document.getElementById("activity-window");
+
+const heatmapCanvas = document.createElement("canvas");
+heatmapCanvas.width = width;
+heatmapCanvas.height = height;
+const heatmapCtx = heatmapCanvas.getContext('2d');
+
+const isHeatmap = () => {
+    return renderModeSelect.value === "heatmap";
+}
+
+const drawHeatmap = () => {
+    const rgba = new Uint8ClampedArray(simulation.render_heatmap_rgba());
+    heatmapCtx.putImageData(new ImageData(rgba, width, height), 0, 0);
+
+    ctx.imageSmoothingEnabled = false;
+    ctx.drawImage(heatmapCanvas, 1, 1, (CELL_SIZE + 1) * width, (CELL_SIZE + 1) * height);
+}
+
+//表示方法に合わせて盤面全体を描き直す
+const redraw = () => {
+    if (isHeatmap()) {
+        drawHeatmap();
+        return;
+    }
+    initCells();
+    drawBG();
+    drawGrid();
+    drawCells();
+}
+
+//ヒートマップを表示している間だけ変化回数を数える
+const updateActivity = () => {
+    if (isHeatmap()) {
+        simulation.enable_activity(Number(activityWindowInput.value));
+    } else {
+        simulation.disable_activity();
+    }
+    redraw();
+}
+renderModeSelect.addEventListener("change", updateActivity);
+activityWindowInput.addEventListener("change", updateActivity);
+
 const playPauseButton = document.getElementById("play-pause");
 
 const play = () => {
     playPauseButton.textContent = "⏸";
 
-    drawGrid();
-    drawCells();
-    renderLoop();
+    redraw();
+    simulation.play();
 }
 
 const pause = () => {
     playPauseButton.textContent = "▶";
 
-    //これでキューイングされてる次フレームをキャンセルするっぽい
-    cancelAnimationFrame(animationId);
-    animationId = null;
+    simulation.pause();
 }
 
 playPauseButton.addEventListener("click", e => {
@@ -185,48 +215,50 @@ playPauseButton.addEventListener("click", e => {
     }
 })
 
-//再生中はrequestAnimationFrameが入り続け、一時停止中はnullになるような識別子
-let animationId = null;
-
 const resetButton = document.getElementById("reset");
 resetButton.addEventListener("click", e => {
-    universe.reset();
-    initCells();
-    drawBG();
-    drawGrid();
-    drawCells();
+    simulation.reset();
+    redraw();
 
 })
 
-const renderLoop = () => {
-
-    //debuggerを有効にするとブラウザの実行中にブレークポイントを設定できる　便利！
-    //debugger;
-
+//描画ループ自体はRust側(Simulation)が回していて、世代が進んだフレームだけここが呼ばれる
+simulation.set_on_frame(ticks => {
     fps.render();
+    if (isHeatmap()) {
+        drawHeatmap();
+    } else {
+        drawCellsDelta();
+    }
 
-    universe.tick();
-
-    //drawBG();
-    //drawGrid();
-    //drawCells();
-    drawCellsDelta();
-
-
-
+    //run_untilなどでRust側が止まったときにボタンの表示を合わせる
+    if (isPaused()) {
+        playPauseButton.textContent = "▶";
+    }
+});
 
+const stepButton = document.getElementById("step");
+stepButton.addEventListener("click", e => {
+    if (!isPaused()) {
+        pause();
+    }
+    simulation.step(1);
+})
 
-    //animationIdに渡しつつ次のフレームを実行
-    animationId = requestAnimationFrame(renderLoop);
-};
+//速度は1秒あたりの世代数 速いときは1フレームで複数世代進む
+const speedInput = document.getElementById("speed");
+speedInput.addEventListener("input", e => {
+    simulation.set_speed(Number(speedInput.value));
+})
+simulation.set_speed(Number(speedInput.value));
 
 //現在一時停止中か否かを返す
 const isPaused = () => {
-    return animationId === null;
+    return !simulation.is_playing();
 }
 
-//canvasがクリックされたときにセルを取得してそのセルの生存状況を判定させる
-canvas.addEventListener("click", e => {
+//マウス位置からセル番地を割り出す
+const cellFromEvent = e => {
 
     //canvasが存在する矩形領域を取得する
     const boundingRect = canvas.getBoundingClientRect();
@@ -240,25 +272,56 @@ canvas.addEventListener("click", e => {
     const localY = (e.clientY - boundingRect.top) * scaleY;
 
     //得られたローカル座標からセル番地を割り出す
-    const row = Math.min(Math.floor(localY / (CELL_SIZE + 1)), height - 1);
-    const col = Math.min(Math.floor(localX / (CELL_SIZE + 1)), width - 1);
+    const row = Math.max(Math.min(Math.floor(localY / (CELL_SIZE + 1)), height - 1), 0);
+    const col = Math.max(Math.min(Math.floor(localX / (CELL_SIZE + 1)), width - 1), 0);
 
-    universe.toggle_cell(row, col);
-    const idx = getIndex(row, col);
+    return [row, col];
+}
 
-    if (cellMem[idx] === Cell.Alive) {
-        cellMem[idx] = Cell.Dead;
-    } else {
-        cellMem[idx] = Cell.Alive;
+//ブラシで塗ったあとはcellMemを今のセルに合わせて描き直す
+const redrawAfterPaint = () => {
+    if (isHeatmap()) {
+        drawHeatmap();
+        return;
     }
+    initCells();
+    drawCells();
+}
 
-    if (isPaused()) {
+//ドラッグで描けるブラシ 塗る処理(ドラッグ間の線の補間を含む)はRust側でまとめて行う
+const brush = Brush.new();
+const brushSizeInput = document.getElementById("brush-size");
+const brushShapeSelect = document.getElementById("brush-shape");
+const brushModeSelect = document.getElementById("brush-mode");
 
-        drawBG();
-        drawGrid();
-        drawCells();
+brushSizeInput.addEventListener("input", e => {
+    brush.set_size(Number(brushSizeInput.value));
+})
+brushShapeSelect.addEventListener("change", e => {
+    brush.set_shape(brushShapeSelect.value === "circle" ? BrushShape.Circle : BrushShape.Square);
+})
+brushModeSelect.addEventListener("change", e => {
+    brush.set_mode(brushModeSelect.value === "erase" ? BrushMode.Erase : BrushMode.Draw);
+})
+
+canvas.addEventListener("mousedown", e => {
+    const [row, col] = cellFromEvent(e);
+    simulation.press_brush(brush, row, col);
+    redrawAfterPaint();
+})
+
+canvas.addEventListener("mousemove", e => {
+    if ((e.buttons & 1) === 0) {
+        return;
     }
+    const [row, col] = cellFromEvent(e);
+    simulation.drag_brush(brush, row, col);
+    redrawAfterPaint();
+})
 
+//canvasの外でボタンを離してもドラッグを終わらせる
+window.addEventListener("mouseup", e => {
+    brush.release();
 })
 
 //fpsカウンター
@@ -306,4 +369,5 @@ const fps = new class {
 };
 
 initCells();
+simulation.start();
 play();
\ No newline at end of file